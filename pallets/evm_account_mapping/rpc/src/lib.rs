@@ -0,0 +1,421 @@
+// This file is part of EVM Account Mapping Pallet.
+
+// Copyright (C) HashForest Technology Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! RPC interface for the EVM account mapping pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use pallet_evm_account_mapping::{
+	typed_data, MetaCallTerms, MetaCallValidityError, Nonce, NonceKey,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{crypto::AccountId32, Bytes, H160, H256};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+
+pub use pallet_evm_account_mapping::runtime_api::EvmAccountMappingApi as EvmAccountMappingRuntimeApi;
+
+#[rpc(client, server)]
+pub trait EvmAccountMappingApi<BlockHash, Balance, BlockNumber> {
+	/// The nonce of the next meta-call of the account.
+	#[method(name = "evmAccountMapping_nonce")]
+	fn nonce(&self, who: AccountId32, at: Option<BlockHash>) -> RpcResult<Nonce>;
+
+	/// The nonce of the next meta-call of the account in the lane `nonce_key`.
+	#[method(name = "evmAccountMapping_laneNonce")]
+	fn lane_nonce(
+		&self,
+		who: AccountId32,
+		nonce_key: NonceKey,
+		at: Option<BlockHash>,
+	) -> RpcResult<Nonce>;
+
+	/// The account mapped from the EVM public key, or from the 20 bytes EVM address if the
+	/// `AddressConverter` supports it.
+	#[method(name = "evmAccountMapping_accountOf")]
+	fn account_of(
+		&self,
+		evm_public_key_or_address: Bytes,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<AccountId32>>;
+
+	/// The message hash the wallet signs, it is the v2 typed data if `terms` is set.
+	///
+	/// The on-chain nonce of the signed lane is used if `nonce` isn't set.
+	#[method(name = "evmAccountMapping_messageHash")]
+	fn message_hash(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		terms: Option<MetaCallTerms<Balance, BlockNumber>>,
+		at: Option<BlockHash>,
+	) -> RpcResult<H256>;
+
+	/// The JSON typed data the wallet signs with `eth_signTypedData_v4`, it is the v2 typed
+	/// data if `terms` is set.
+	///
+	/// The on-chain nonce of the signed lane is used if `nonce` isn't set.
+	#[method(name = "evmAccountMapping_typedData")]
+	fn typed_data(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		terms: Option<MetaCallTerms<Balance, BlockNumber>>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+
+	/// The JSON `SubstrateBatch` typed data of a `meta_batch_call`, `calls` are the encoded
+	/// calls.
+	///
+	/// The on-chain nonce is used if `nonce` isn't set.
+	#[method(name = "evmAccountMapping_batchTypedData")]
+	fn batch_typed_data(
+		&self,
+		who: AccountId32,
+		calls: Vec<Bytes>,
+		nonce: Option<Nonce>,
+		atomic: bool,
+		max_fee: Balance,
+		valid_until: BlockNumber,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+
+	/// The JSON `SponsoredSubstrateCall` typed data of a `sponsored_meta_call`, `sponsor` is
+	/// `None` if any sponsor may submit it.
+	///
+	/// The on-chain nonce is used if `nonce` isn't set.
+	#[method(name = "evmAccountMapping_sponsoredTypedData")]
+	fn sponsored_typed_data(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		valid_until: BlockNumber,
+		sponsor: Option<AccountId32>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+
+	/// The JSON `CancelMetaNonce` typed data of a `meta_cancel_nonce`.
+	#[method(name = "evmAccountMapping_cancelTypedData")]
+	fn cancel_typed_data(
+		&self,
+		who: AccountId32,
+		nonce_key: NonceKey,
+		up_to: Nonce,
+		valid_until: BlockNumber,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+
+	/// The JSON typed data of a `meta_call_template`, `template` is the encoded call template.
+	///
+	/// The on-chain nonce of `who` is used if `nonce` isn't set.
+	#[method(name = "evmAccountMapping_templateTypedData")]
+	fn template_typed_data(
+		&self,
+		who: AccountId32,
+		template: Bytes,
+		nonce: Option<Nonce>,
+		valid_until: BlockNumber,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+
+	/// The message of the `InvalidTransaction::Custom` code of a rejected meta-call, `None` if
+	/// the code isn't a `MetaCallValidityError`.
+	#[method(name = "evmAccountMapping_validityErrorMessage")]
+	fn validity_error_message(&self, code: u8) -> RpcResult<Option<String>>;
+}
+
+/// Provides RPC methods to query the EVM account mapping pallet.
+pub struct EvmAccountMapping<C, P> {
+	client: Arc<C>,
+	_marker: PhantomData<P>,
+}
+
+impl<C, P> EvmAccountMapping<C, P> {
+	/// Creates a new instance of the EvmAccountMapping Rpc helper.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Display) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		Error::RuntimeError.into(),
+		message,
+		Some(e.to_string()),
+	))
+	.into()
+}
+
+impl<C, Block, Balance, BlockNumber>
+	EvmAccountMappingApiServer<<Block as BlockT>::Hash, Balance, BlockNumber>
+	for EvmAccountMapping<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: EvmAccountMappingRuntimeApi<Block, AccountId32, Balance, BlockNumber>,
+	Balance: Codec + Copy + UniqueSaturatedInto<u128> + Serialize + DeserializeOwned + Send + Sync + 'static,
+	BlockNumber:
+		Codec + Copy + UniqueSaturatedInto<u64> + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+	fn nonce(&self, who: AccountId32, at: Option<<Block as BlockT>::Hash>) -> RpcResult<Nonce> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.account_nonce(at_hash, who)
+			.map_err(|e| runtime_error("Unable to query the nonce.", e))
+	}
+
+	fn lane_nonce(
+		&self,
+		who: AccountId32,
+		nonce_key: NonceKey,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Nonce> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.lane_nonce(at_hash, who, nonce_key)
+			.map_err(|e| runtime_error("Unable to query the nonce.", e))
+	}
+
+	fn account_of(
+		&self,
+		evm_public_key_or_address: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<AccountId32>> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let evm_public_key_or_address = evm_public_key_or_address.to_vec();
+		if evm_public_key_or_address.len() == H160::len_bytes() {
+			api.account_of_address(at_hash, H160::from_slice(&evm_public_key_or_address))
+		} else {
+			api.account_of(at_hash, evm_public_key_or_address)
+		}
+		.map_err(|e| runtime_error("Unable to convert the account.", e))
+	}
+
+	fn message_hash(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		terms: Option<MetaCallTerms<Balance, BlockNumber>>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<H256> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let nonce = match nonce {
+			Some(nonce) => nonce,
+			None => {
+				let nonce_key = terms.as_ref().map_or(0, |terms| terms.nonce_key);
+				self.lane_nonce(who.clone(), nonce_key, Some(at_hash))?
+			},
+		};
+		match terms {
+			Some(terms) =>
+				api.eip712_message_hash_v2(at_hash, who, call_data.to_vec(), nonce, terms),
+			None => api.eip712_message_hash(at_hash, who, call_data.to_vec(), nonce),
+		}
+		.map(H256)
+		.map_err(|e| runtime_error("Unable to compute the message hash.", e))
+	}
+
+	fn typed_data(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		terms: Option<MetaCallTerms<Balance, BlockNumber>>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Value> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let nonce = match nonce {
+			Some(nonce) => nonce,
+			None => {
+				let nonce_key = terms.as_ref().map_or(0, |terms| terms.nonce_key);
+				self.lane_nonce(who.clone(), nonce_key, Some(at_hash))?
+			},
+		};
+		let context = api
+			.typed_data_context(at_hash)
+			.map_err(|e| runtime_error("Unable to query the typed data context.", e))?;
+		let description = match terms {
+			Some(_) => api
+				.describe_call(at_hash, call_data.to_vec())
+				.map_err(|e| runtime_error("Unable to describe the call.", e))?
+				.ok_or_else(|| runtime_error("Unable to describe the call.", "undecodable call data"))?,
+			None => Default::default(),
+		};
+		let terms = terms.as_ref().map(|terms| (terms, &description));
+		Ok(typed_data::build(&context, &who, &call_data, nonce, terms))
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn batch_typed_data(
+		&self,
+		who: AccountId32,
+		calls: Vec<Bytes>,
+		nonce: Option<Nonce>,
+		atomic: bool,
+		max_fee: Balance,
+		valid_until: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Value> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let nonce = match nonce {
+			Some(nonce) => nonce,
+			None => self.nonce(who.clone(), Some(at_hash))?,
+		};
+		let context = api
+			.typed_data_context(at_hash)
+			.map_err(|e| runtime_error("Unable to query the typed data context.", e))?;
+		let calls: Vec<Vec<u8>> = calls.into_iter().map(|call| call.to_vec()).collect();
+		Ok(typed_data::build_batch(
+			&context,
+			&who,
+			&calls,
+			nonce,
+			atomic,
+			max_fee.unique_saturated_into(),
+			valid_until.unique_saturated_into(),
+		))
+	}
+
+	fn sponsored_typed_data(
+		&self,
+		who: AccountId32,
+		call_data: Bytes,
+		nonce: Option<Nonce>,
+		valid_until: BlockNumber,
+		sponsor: Option<AccountId32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Value> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let nonce = match nonce {
+			Some(nonce) => nonce,
+			None => self.nonce(who.clone(), Some(at_hash))?,
+		};
+		let context = api
+			.typed_data_context(at_hash)
+			.map_err(|e| runtime_error("Unable to query the typed data context.", e))?;
+		let description = api
+			.describe_call(at_hash, call_data.to_vec())
+			.map_err(|e| runtime_error("Unable to describe the call.", e))?
+			.ok_or_else(|| runtime_error("Unable to describe the call.", "undecodable call data"))?;
+		Ok(typed_data::build_sponsored(
+			&context,
+			&who,
+			&call_data,
+			&description,
+			nonce,
+			valid_until.unique_saturated_into(),
+			sponsor.as_ref(),
+		))
+	}
+
+	fn cancel_typed_data(
+		&self,
+		who: AccountId32,
+		nonce_key: NonceKey,
+		up_to: Nonce,
+		valid_until: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Value> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let context = api
+			.typed_data_context(at_hash)
+			.map_err(|e| runtime_error("Unable to query the typed data context.", e))?;
+		Ok(typed_data::build_cancel(
+			&context,
+			&who,
+			nonce_key,
+			up_to,
+			valid_until.unique_saturated_into(),
+		))
+	}
+
+	fn template_typed_data(
+		&self,
+		who: AccountId32,
+		template: Bytes,
+		nonce: Option<Nonce>,
+		valid_until: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Value> {
+		let api = self.client.runtime_api();
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let nonce = match nonce {
+			Some(nonce) => nonce,
+			None => self.nonce(who, Some(at_hash))?,
+		};
+		let context = api
+			.typed_data_context(at_hash)
+			.map_err(|e| runtime_error("Unable to query the typed data context.", e))?;
+		let template = api
+			.template_members(at_hash, template.to_vec())
+			.map_err(|e| runtime_error("Unable to decode the call template.", e))?
+			.ok_or_else(|| {
+				runtime_error("Unable to decode the call template.", "undecodable template")
+			})?;
+		Ok(typed_data::build_template_members(
+			&context,
+			&template,
+			nonce,
+			valid_until.unique_saturated_into(),
+		))
+	}
+
+	fn validity_error_message(&self, code: u8) -> RpcResult<Option<String>> {
+		Ok(MetaCallValidityError::try_from(code).ok().map(|error| error.message().into()))
+	}
+}
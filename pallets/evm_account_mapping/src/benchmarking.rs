@@ -25,36 +25,392 @@ use frame_benchmarking::v2::*;
 use frame_system::RawOrigin;
 
 use codec::Decode;
-use sp_core::crypto::Ss58Codec;
-use sp_runtime::traits::{Bounded, TrailingZeroInput};
+use frame_support::traits::UnfilteredDispatchable;
+use sp_core::{crypto::KeyTypeId, ecdsa};
+use sp_runtime::traits::{Bounded, TrailingZeroInput, ValidateUnsigned};
+
+/// The keystore type of the keys signing the benchmarked meta-calls.
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"evma");
 
 #[allow(dead_code)]
 fn assert_last_event<T: Config>(generic_event: <T as Config>::RuntimeEvent) {
 	frame_system::Pallet::<T>::assert_last_event(generic_event.into());
 }
 
+/// Generates an ECDSA key in the keystore, and the account it is mapped to.
+fn evm_signer<T>() -> (ecdsa::Public, T::AccountId)
+where
+	T: Config + frame_system::Config<AccountId = sp_runtime::AccountId32>,
+{
+	let public = sp_io::crypto::ecdsa_generate(KEY_TYPE, None);
+	// The account is mapped from the public key the signature recovers
+	let message_hash = [0u8; 32];
+	let who = ThisPallet::<T>::recover_signer(&sign(&public, &message_hash), &message_hash)
+		.expect("Valid signature");
+	(public, who)
+}
+
+fn sign(public: &ecdsa::Public, message_hash: &[u8; 32]) -> EIP712Signature {
+	let signature = sp_io::crypto::ecdsa_sign_prehashed(KEY_TYPE, public, message_hash)
+		.expect("The key is in the keystore");
+	AsRef::<[u8]>::as_ref(&signature).try_into().expect("Valid signature")
+}
+
+/// The `system.remarkWithEvent("Hello")` call and its encoding.
+fn remark_call<T: Config>() -> (<T as Config>::RuntimeCall, Vec<u8>) {
+	let call_data = hex::decode("00071448656c6c6f").expect("Valid"); // system.remarkWithEvent("Hello")
+	let call =
+		<T as Config>::RuntimeCall::decode(&mut TrailingZeroInput::new(&call_data)).expect("Valid");
+	(call, call_data)
+}
+
 #[benchmarks( where PaymentBalanceOf<T>: FixedPointOperand, <T as frame_system::Config>::RuntimeCall: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>, T: frame_system::Config<AccountId = sp_runtime::AccountId32>,)]
 mod benchmarks {
 	use super::*;
 
+	/// `pre_dispatch` verifies the signature again and runs the checks of `validate_unsigned`
+	/// when the meta-call is included, so it is measured along with the dispatch.
 	#[benchmark]
 	fn meta_call() -> Result<(), BenchmarkError> {
-		let account =
-			T::AccountId::from_ss58check("5DT96geTS2iLpkH8fAhYAAphNpxddKCV36s5ShVFavf1xQiF")
-				.unwrap();
-		let call_data = hex::decode("00071448656c6c6f").expect("Valid"); // system.remarkWithEvent("Hello")
-		let call = <T as frame_system::Config>::RuntimeCall::decode(&mut TrailingZeroInput::new(
-			&call_data,
-		))
-		.expect("Valid");
-		let nonce: u64 = 0;
-		let signature: [u8; 65] = hex::decode("37cb6ff8e296d7e476ee13a6cfababe788217519d428fcc723b482dc97cb4d1359a8d1c020fe3cebc1d06a67e61b1f0e296739cecacc640b0ba48e8a7555472e1b").expect("Decodable").try_into().expect("Valid");
-
+		let (public, account) = evm_signer::<T>();
+		let (call, call_data) = remark_call::<T>();
+		let nonce: Nonce = 0;
+		let message_hash = ThisPallet::<T>::eip712_message_hash(account.clone(), &call_data, nonce);
+		let signature = sign(&public, &message_hash);
 		T::Currency::make_free_balance_be(&account, BalanceOf::<T>::max_value() / 2u32.into());
+		let unsigned_call = Call::<T>::meta_call {
+			who: account.clone(),
+			call: Box::new(call),
+			nonce,
+			signature,
+			tip: None,
+		};
+
+		#[block]
+		{
+			ThisPallet::<T>::pre_dispatch(&unsigned_call)
+				.map_err(|_| BenchmarkError::Stop("Invalid meta-call"))?;
+			unsigned_call.dispatch_bypass_filter(RawOrigin::None.into())?;
+		}
+
+		assert_eq!(AccountNonce::<T>::get(&account), 1);
+		Ok(())
+	}
+
+	/// A `meta_call_v2` paid by a paymaster with a `user_quota` for a new user, so the budget,
+	/// the users and the deposit of the paymaster, `PaymasterSpent` and the reserve of the sponsor
+	/// are all updated.
+	#[benchmark]
+	fn meta_call_paymaster() -> Result<(), BenchmarkError> {
+		let (public, account) = evm_signer::<T>();
+		let (call, call_data) = remark_call::<T>();
+		let sponsor: T::AccountId = whitelisted_caller();
+		let budget = BalanceOf::<T>::max_value() / 4u32.into();
+		T::Currency::make_free_balance_be(&sponsor, BalanceOf::<T>::max_value() / 2u32.into());
+		T::Currency::reserve(&sponsor, budget)?;
+		Paymasters::<T>::insert(
+			&sponsor,
+			Paymaster {
+				budget,
+				allowed_calls: Default::default(),
+				user_quota: Some(budget),
+				expires_at: None,
+				deposit: Zero::zero(),
+				users: 0,
+			},
+		);
+		let terms = MetaCallTerms {
+			tip: Zero::zero(),
+			max_fee: PaymentBalanceOf::<T>::max_value(),
+			max_relayer_fee: Zero::zero(),
+			valid_after: Zero::zero(),
+			valid_until: Zero::zero(),
+			paymaster: Some(sponsor.clone()),
+			nonce_key: 0,
+		};
+		let nonce: Nonce = 0;
+		let message_hash =
+			ThisPallet::<T>::eip712_message_hash_v2(account.clone(), &call_data, nonce, &terms);
+		let signature = sign(&public, &message_hash);
+		let unsigned_call = Call::<T>::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call),
+			nonce,
+			signature,
+			terms,
+		};
+
+		#[block]
+		{
+			ThisPallet::<T>::pre_dispatch(&unsigned_call)
+				.map_err(|_| BenchmarkError::Stop("Invalid meta-call"))?;
+			unsigned_call.dispatch_bypass_filter(RawOrigin::None.into())?;
+		}
+
+		assert_eq!(AccountNonce::<T>::get(&account), 1);
+		assert!(PaymasterSpent::<T>::contains_key(&sponsor, &account));
+		Ok(())
+	}
+
+	/// A `relayed_meta_call` paid by a paymaster like `meta_call_paymaster`, the relayer is
+	/// credited its share of the service fee.
+	#[benchmark]
+	fn relayed_meta_call() -> Result<(), BenchmarkError> {
+		let (public, account) = evm_signer::<T>();
+		let (call, call_data) = remark_call::<T>();
+		let relayer: T::AccountId = whitelisted_caller();
+		let sponsor: T::AccountId = frame_benchmarking::account("sponsor", 0, 0);
+		let budget = BalanceOf::<T>::max_value() / 4u32.into();
+		T::Currency::make_free_balance_be(&sponsor, BalanceOf::<T>::max_value() / 2u32.into());
+		T::Currency::reserve(&sponsor, budget)?;
+		Paymasters::<T>::insert(
+			&sponsor,
+			Paymaster {
+				budget,
+				allowed_calls: Default::default(),
+				user_quota: Some(budget),
+				expires_at: None,
+				deposit: Zero::zero(),
+				users: 0,
+			},
+		);
+		let terms = MetaCallTerms {
+			tip: Zero::zero(),
+			max_fee: PaymentBalanceOf::<T>::max_value(),
+			max_relayer_fee: PaymentBalanceOf::<T>::max_value(),
+			valid_after: Zero::zero(),
+			valid_until: Zero::zero(),
+			paymaster: Some(sponsor.clone()),
+			nonce_key: 0,
+		};
+		let nonce: Nonce = 0;
+		let message_hash =
+			ThisPallet::<T>::eip712_message_hash_v2(account.clone(), &call_data, nonce, &terms);
+		let signature = sign(&public, &message_hash);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(relayer), account.clone(), Box::new(call), nonce, signature, terms);
+
+		assert_eq!(AccountNonce::<T>::get(&account), 1);
+		assert!(PaymasterSpent::<T>::contains_key(&sponsor, &account));
+		Ok(())
+	}
+
+	/// A `meta_call` covered by the free quota, the first one of the period so
+	/// `FreeCallsInPeriod`, `FreeCallsUsed` and `OldestFreeCallPeriod` are all written.
+	#[benchmark]
+	fn meta_call_free_quota() -> Result<(), BenchmarkError> {
+		let (public, account) = evm_signer::<T>();
+		let (call, call_data) = remark_call::<T>();
+		if ThisPallet::<T>::free_calls_left(&account) == 0 ||
+			!T::FreeCallFilter::contains(&call.clone().into())
+		{
+			// The runtime has no free quota for the call
+			return Err(BenchmarkError::Skip)
+		}
+		let nonce: Nonce = 0;
+		let message_hash = ThisPallet::<T>::eip712_message_hash(account.clone(), &call_data, nonce);
+		let signature = sign(&public, &message_hash);
+		let unsigned_call = Call::<T>::meta_call {
+			who: account.clone(),
+			call: Box::new(call),
+			nonce,
+			signature,
+			tip: None,
+		};
+
+		#[block]
+		{
+			ThisPallet::<T>::pre_dispatch(&unsigned_call)
+				.map_err(|_| BenchmarkError::Stop("Invalid meta-call"))?;
+			unsigned_call.dispatch_bypass_filter(RawOrigin::None.into())?;
+		}
+
+		assert_eq!(AccountNonce::<T>::get(&account), 1);
+		assert!(OldestFreeCallPeriod::<T>::exists());
+		Ok(())
+	}
+
+	/// Prunes `n` entries of a past period of the free quota from `FreeCallsUsed`, as
+	/// `on_initialize` does.
+	#[benchmark]
+	fn clean_up_free_call_periods(n: Linear<0, MAX_META_CALL_RATE_CLEANUP>) {
+		let past_period = frame_system::pallet_prelude::BlockNumberFor::<T>::zero();
+		for i in 0..n {
+			let user: T::AccountId = account("user", i, 0);
+			FreeCallsUsed::<T>::insert(past_period, user, 1);
+		}
+		OldestFreeCallPeriod::<T>::put(past_period);
+		let period_length = T::FreeCallPeriod::get().max(sp_runtime::traits::One::one());
+		frame_system::Pallet::<T>::set_block_number(period_length);
+
+		#[block]
+		{
+			ThisPallet::<T>::clean_up_free_call_periods();
+		}
+
+		assert_eq!(FreeCallsUsed::<T>::iter_prefix(past_period).count(), 0);
+	}
+
+	/// One signature recovery, `Pallet::other_domains_weight` scales it by the accepted EIP-712
+	/// domains.
+	#[benchmark]
+	fn verify_signer() -> Result<(), BenchmarkError> {
+		let (public, account) = evm_signer::<T>();
+		let struct_hash = [1u8; 32];
+		let message_hash =
+			ThisPallet::<T>::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash);
+		let signature = sign(&public, &message_hash);
+
+		#[block]
+		{
+			ThisPallet::<T>::verify_signer(&account, &signature, &struct_hash)
+				.map_err(|_| BenchmarkError::Stop("Invalid signature"))?;
+		}
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn set_eip712_domain() -> Result<(), BenchmarkError> {
+		let origin = T::EIP712DomainOrigin::try_successful_origin()
+			.map_err(|_| BenchmarkError::Weightless)?;
+		let domain = EIP712DomainParams {
+			name: b"Substrate".to_vec(),
+			version: b"2".to_vec(),
+			chain_id: sp_core::U256::from(1),
+			verifying_contract: sp_core::H160::from([0u8; 20]),
+		};
+		let grace_period = Some(100u32.into());
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, domain, grace_period);
+
+		assert!(PreviousEIP712Domain::<T>::get().is_some());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn register_paymaster() -> Result<(), BenchmarkError> {
+		let sponsor: T::AccountId = whitelisted_caller();
+		let policy = CallPolicy {
+			pallet_name: alloc::vec![0u8; MAX_CALL_NAME_LEN as usize].try_into().expect("Bounded"),
+			call_name: Some(
+				alloc::vec![0u8; MAX_CALL_NAME_LEN as usize].try_into().expect("Bounded"),
+			),
+		};
+		let allowed_calls = alloc::vec![policy; T::MaxPaymasterCalls::get() as usize]
+			.try_into()
+			.expect("Bounded");
+		T::Currency::make_free_balance_be(&sponsor, BalanceOf::<T>::max_value() / 2u32.into());
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(sponsor.clone()),
+			BalanceOf::<T>::max_value() / 4u32.into(),
+			allowed_calls,
+			Some(BalanceOf::<T>::max_value()),
+			Some(100u32.into()),
+		);
+
+		assert!(Paymasters::<T>::contains_key(&sponsor));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn unregister_paymaster(
+		u: Linear<0, { T::MaxPaymasterUsers::get() }>,
+	) -> Result<(), BenchmarkError> {
+		let sponsor: T::AccountId = whitelisted_caller();
+		let budget = BalanceOf::<T>::max_value() / 4u32.into();
+		T::Currency::make_free_balance_be(&sponsor, BalanceOf::<T>::max_value() / 2u32.into());
+		T::Currency::reserve(&sponsor, budget)?;
+		Paymasters::<T>::insert(
+			&sponsor,
+			Paymaster {
+				budget,
+				allowed_calls: Default::default(),
+				user_quota: Some(budget),
+				expires_at: None,
+				deposit: Zero::zero(),
+				users: u,
+			},
+		);
+		for i in 0..u {
+			let user: T::AccountId = account("user", i, 0);
+			PaymasterSpent::<T>::insert(&sponsor, user, budget);
+		}
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(sponsor.clone()));
+
+		assert!(!Paymasters::<T>::contains_key(&sponsor));
+		assert_eq!(PaymasterSpent::<T>::iter_prefix(&sponsor).count(), 0);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn cancel_meta_nonce() -> Result<(), BenchmarkError> {
+		let caller: T::AccountId = whitelisted_caller();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), 1, 100);
+
+		assert_eq!(LaneNonce::<T>::get(&caller, 1), 101);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn pause() -> Result<(), BenchmarkError> {
+		let origin =
+			T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let unpause_at = Some(100u32.into());
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, unpause_at);
+
+		assert!(Paused::<T>::get());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn unpause() -> Result<(), BenchmarkError> {
+		let origin =
+			T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		Paused::<T>::put(true);
+		UnpauseAt::<T>::put(frame_system::pallet_prelude::BlockNumberFor::<T>::from(100u32));
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin);
+
+		assert!(!Paused::<T>::get());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn freeze_account() -> Result<(), BenchmarkError> {
+		let origin =
+			T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let who: T::AccountId = whitelisted_caller();
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, who.clone());
+
+		assert!(FrozenAccounts::<T>::contains_key(&who));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn thaw_account() -> Result<(), BenchmarkError> {
+		let origin =
+			T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let who: T::AccountId = whitelisted_caller();
+		FrozenAccounts::<T>::insert(&who, ());
 
 		#[extrinsic_call]
-		_(RawOrigin::None, account, Box::new(call.into()), nonce, signature, None);
+		_(origin as T::RuntimeOrigin, who.clone());
 
+		assert!(!FrozenAccounts::<T>::contains_key(&who));
 		Ok(())
 	}
 
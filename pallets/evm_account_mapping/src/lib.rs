@@ -23,6 +23,11 @@ pub use pallet::*;
 
 mod eip712;
 mod encode;
+pub mod runtime_api;
+#[cfg(feature = "std")]
+pub mod typed_data;
+pub mod validity;
+pub use validity::MetaCallValidityError;
 
 #[cfg(test)]
 mod mock;
@@ -50,32 +55,327 @@ macro_rules! log {
 }
 
 use alloc::{boxed::Box, vec::Vec};
-use codec::Decode;
-use frame_support::{dispatch::{DispatchInfo, GetDispatchInfo, PostDispatchInfo, RawOrigin}, Parameter, traits::{
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{dispatch::{DispatchInfo, GetDispatchInfo, PostDispatchInfo, RawOrigin}, BoundedVec, Parameter, traits::{
 	tokens::{Fortitude, Preservation},
 	fungible::Inspect as InspectFungible,
-	Contains, Imbalance, OriginTrait,
-	Currency,
+	ConstU32, Contains, GetCallMetadata, Imbalance, OriginTrait,
+	Currency, ReservableCurrency,
 }, weights::Weight};
 use pallet_transaction_payment::OnChargeTransaction;
 use sp_core::crypto::AccountId32;
 use sp_io::hashing::blake2_256;
-use sp_runtime::{traits::Dispatchable, FixedPointOperand};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use sp_runtime::{
+	traits::{Dispatchable, Zero},
+	FixedPointOperand, Perbill, RuntimeDebug,
+};
 
 type PaymentOnChargeTransaction<T> = <T as pallet_transaction_payment::Config>::OnChargeTransaction;
 
 type PaymentBalanceOf<T> = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
 
 type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+pub type TemplateOf<T> =
+	<<T as Config>::CallTemplates as CallTemplates<<T as Config>::RuntimeCall>>::Template;
 type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::NegativeImbalance;
 pub type EIP712ChainID = sp_core::U256;
 pub type EIP712VerifyingContractAddress = sp_core::H160;
+pub type EIP712Salt = [u8; 32];
 pub type EIP712Signature = [u8; 65];
 
 pub type Nonce = u64;
+/// The key of a nonce lane, the meta-calls of different lanes don't wait for each other.
+pub type NonceKey = u64;
 pub type AccountId32Bytes = [u8; 32];
 pub type Keccak256Signature = [u8; 32];
 
+/// The legacy EIP-712 `SubstrateCall` type, the tip isn't covered by the signature.
+pub const SUBSTRATE_CALL_TYPE_V1: &str = "SubstrateCall(string who,bytes callData,uint64 nonce)";
+
+/// The EIP-712 `SubstrateCall` type which also covers the description of the call, the tip, the
+/// maximum total fee and the validity window, zero for no bound.
+///
+/// `genesisHash`, `specVersion` and `txVersion` bind the signature to the chain and the runtime
+/// version like `CheckGenesis`, `CheckSpecVersion` and `CheckTxVersion`. They aren't passed in the
+/// extrinsic, the pallet fills them with the on-chain values, so a signature made for another
+/// chain, or before a runtime upgrade, is rejected. The other typed data end with them too.
+pub const SUBSTRATE_CALL_TYPE_V2: &str =
+	"SubstrateCall(string who,string palletName,string callName,string callArgs,bytes callData,uint64 nonce,uint64 nonceKey,uint256 tip,uint256 maxFee,uint256 maxRelayerFee,uint64 validAfter,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion,string paymaster)";
+
+/// The EIP-712 `CancelMetaNonce` type of `meta_cancel_nonce`, invalidates the meta-calls of the
+/// lane `nonceKey` up to the nonce `upTo`.
+///
+/// `validUntil`, `genesisHash`, `specVersion` and `txVersion` work like in
+/// [`SUBSTRATE_CALL_TYPE_V2`].
+pub const CANCEL_META_NONCE_TYPE: &str =
+	"CancelMetaNonce(string who,uint64 nonceKey,uint64 upTo,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion)";
+
+/// The terms signed along with the call in the `SubstrateCall` v2 typed data.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaCallTerms<Balance, BlockNumber> {
+	/// The tip for the block author.
+	pub tip: Balance,
+	/// The maximum total fee the signer agrees to pay, includes the tip and the service fee.
+	pub max_fee: Balance,
+	/// The maximum share of the service fee the submitting relayer may take, zero means nothing.
+	#[serde(default)]
+	pub max_relayer_fee: Balance,
+	/// The call can't be included before this block, zero means no lower bound.
+	pub valid_after: BlockNumber,
+	/// The call can't be included after this block, zero means no upper bound.
+	pub valid_until: BlockNumber,
+	/// The sponsor whose paymaster pays the fees instead of the signer, see `Paymasters`.
+	#[serde(default)]
+	pub paymaster: Option<AccountId32>,
+	/// The nonce lane, zero is the sequential lane of `account_nonce`, see `LaneNonce`.
+	#[serde(default)]
+	pub nonce_key: NonceKey,
+}
+
+impl<Balance, BlockNumber: sp_runtime::traits::AtLeast32BitUnsigned + Copy>
+	MetaCallTerms<Balance, BlockNumber>
+{
+	/// Whether block `n` is before the validity window.
+	pub fn is_premature_at(&self, n: BlockNumber) -> bool {
+		!self.valid_after.is_zero() && n < self.valid_after
+	}
+
+	/// Whether block `n` is after the validity window.
+	pub fn is_expired_at(&self, n: BlockNumber) -> bool {
+		!self.valid_until.is_zero() && n > self.valid_until
+	}
+}
+
+/// The EIP-712 `SubstrateBatch` type, one signature authorizes several calls.
+///
+/// If `atomic` is set, all calls are reverted once one fails, otherwise the calls are dispatched
+/// best-effort. `maxFee`, `validUntil`, `genesisHash`, `specVersion` and `txVersion` work like in
+/// [`SUBSTRATE_CALL_TYPE_V2`].
+pub const SUBSTRATE_BATCH_TYPE: &str =
+	"SubstrateBatch(string who,bytes[] calls,uint64 nonce,bool atomic,uint256 maxFee,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion)";
+
+/// The EIP-712 `SponsoredSubstrateCall` type of `sponsored_meta_call`, the sponsor pays the fees.
+///
+/// `validUntil`, `genesisHash`, `specVersion` and `txVersion` work like in
+/// [`SUBSTRATE_CALL_TYPE_V2`]. `sponsor` is the SS58 address of the sponsor the user agrees to, or
+/// empty for any sponsor.
+pub const SPONSORED_SUBSTRATE_CALL_TYPE: &str =
+	"SponsoredSubstrateCall(string who,string palletName,string callName,string callArgs,bytes callData,uint64 nonce,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion,string sponsor)";
+
+/// The human-readable description of a call, so the wallet can show what the user approves
+/// instead of the opaque call data.
+///
+/// All fields are UTF-8 strings.
+#[derive(Clone, Default, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct CallDescription {
+	/// The name of the pallet, e.g. `Balances`.
+	pub pallet_name: Vec<u8>,
+	/// The name of the call, e.g. `transfer_keep_alive`.
+	pub call_name: Vec<u8>,
+	/// The rendering of the call arguments, empty if the describer can't render them.
+	pub call_args: Vec<u8>,
+}
+
+/// Describes a call for the v2 typed data.
+///
+/// The pallet derives the description from the call itself when validating, so a relayer can't
+/// show the user a description of another call.
+pub trait CallDescriber<Call> {
+	fn describe(call: &Call) -> CallDescription;
+}
+
+/// Describes the pallet and the call name from the call metadata, without the arguments.
+pub struct CallMetadataDescriber;
+impl<Call: GetCallMetadata> CallDescriber<Call> for CallMetadataDescriber {
+	fn describe(call: &Call) -> CallDescription {
+		let metadata = call.get_call_metadata();
+		CallDescription {
+			pallet_name: metadata.pallet_name.as_bytes().to_vec(),
+			call_name: metadata.function_name.as_bytes().to_vec(),
+			call_args: Vec::new(),
+		}
+	}
+}
+
+/// Decides which calls an account can make with meta-calls.
+///
+/// Unlike a `Contains` filter it also sees the sender and the nonce of the meta-call in its lane,
+/// so a runtime can have per-account policies, e.g. restrict the first meta-calls of an account to
+/// transfers. It is checked in `validate_unsigned`, so a forbidden meta-call never takes block
+/// space, before the nonce is bumped on every dispatch path, and on the dispatch origin, so it also
+/// applies to the calls nested in a batch.
+///
+/// A policy on the nonces must also decide in `can_cancel` whether an account can skip them with
+/// `cancel_meta_nonce` or `meta_cancel_nonce`.
+pub trait MetaCallFilter<AccountId, Call> {
+	/// Whether `who` can make `call` with the meta-call `nonce` of the lane `nonce_key`.
+	fn contains(who: &AccountId, call: &Call, nonce_key: NonceKey, nonce: Nonce) -> bool;
+
+	/// Whether `who` can cancel the meta-calls of the lane `nonce_key` from its next nonce
+	/// `next_nonce` up to `up_to`.
+	fn can_cancel(who: &AccountId, nonce_key: NonceKey, next_nonce: Nonce, up_to: Nonce) -> bool;
+}
+
+/// A `MetaCallFilter` which only looks at the call, e.g. `ContainsCall<Everything>`.
+pub struct ContainsCall<F>(core::marker::PhantomData<F>);
+impl<AccountId, Call, F: Contains<Call>> MetaCallFilter<AccountId, Call> for ContainsCall<F> {
+	fn contains(_who: &AccountId, call: &Call, _nonce_key: NonceKey, _nonce: Nonce) -> bool {
+		F::contains(call)
+	}
+
+	fn can_cancel(_who: &AccountId, _key: NonceKey, _next_nonce: Nonce, _up_to: Nonce) -> bool {
+		true
+	}
+}
+
+/// A member value of a call template, see `CallTemplates`.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum TemplateValue {
+	/// An EIP-712 `string`, in UTF-8.
+	String(Vec<u8>),
+	/// An EIP-712 `uint256`.
+	Uint(sp_core::U256),
+	/// An EIP-712 `bytes`.
+	Bytes(Vec<u8>),
+}
+
+impl TemplateValue {
+	/// The EIP-712 type of the value.
+	pub fn eip712_type(&self) -> &'static str {
+		match self {
+			TemplateValue::String(_) => "string",
+			TemplateValue::Uint(_) => "uint256",
+			TemplateValue::Bytes(_) => "bytes",
+		}
+	}
+
+	fn eip712_token(&self) -> ethabi::Token {
+		match self {
+			TemplateValue::String(value) | TemplateValue::Bytes(value) =>
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(value).to_vec()),
+			TemplateValue::Uint(value) => ethabi::Token::Uint(*value),
+		}
+	}
+}
+
+/// Well-known calls signed as dedicated EIP-712 primary types, e.g.
+/// `Transfer(string to,uint256 amount,uint64 nonce,...)`, so wallets can show what the user
+/// approves member by member.
+///
+/// `SubstrateCall` stays as the fallback for the other calls.
+pub trait CallTemplates<Call> {
+	/// The template and its arguments, usually an enum with a variant per template.
+	type Template: Parameter;
+
+	/// The EIP-712 primary type name and the members of the template, the pallet appends the
+	/// members of `TEMPLATE_TYPE_SUFFIX`.
+	fn members(template: &Self::Template) -> (&'static str, Vec<(&'static str, TemplateValue)>);
+
+	/// Converts the template into the call to dispatch, `None` if the template is invalid.
+	fn into_call(template: Self::Template) -> Option<Call>;
+}
+
+/// No call templates.
+impl<Call> CallTemplates<Call> for () {
+	type Template = ();
+
+	fn members(_template: &()) -> (&'static str, Vec<(&'static str, TemplateValue)>) {
+		("", Vec::new())
+	}
+
+	fn into_call(_template: ()) -> Option<Call> {
+		None
+	}
+}
+
+/// The members the pallet appends to every call template, they work like in
+/// [`SUBSTRATE_CALL_TYPE_V2`].
+pub const TEMPLATE_TYPE_SUFFIX: &str =
+	"uint64 nonce,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion";
+
+/// The encoded EIP-712 type of a call template, the members of `TEMPLATE_TYPE_SUFFIX` are
+/// appended.
+pub fn template_encoded_type(
+	primary_type: &str,
+	members: &[(&str, TemplateValue)],
+) -> alloc::string::String {
+	let mut encoded_type = alloc::format!("{primary_type}(");
+	for (name, value) in members {
+		encoded_type.push_str(value.eip712_type());
+		encoded_type.push(' ');
+		encoded_type.push_str(name);
+		encoded_type.push(',');
+	}
+	encoded_type.push_str(TEMPLATE_TYPE_SUFFIX);
+	encoded_type.push(')');
+	encoded_type
+}
+
+pub type MetaCallTermsOf<T> =
+	MetaCallTerms<PaymentBalanceOf<T>, frame_system::pallet_prelude::BlockNumberFor<T>>;
+
+/// The maximum length of the pallet name or the call name in a `CallPolicy`.
+pub const MAX_CALL_NAME_LEN: u32 = 64;
+
+pub type CallName = BoundedVec<u8, ConstU32<MAX_CALL_NAME_LEN>>;
+
+/// A pallet, or one call of it, sponsored by a paymaster.
+///
+/// The names are matched against the `CallDescription` of the call.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct CallPolicy {
+	/// The name of the pallet, e.g. `Balances`.
+	pub pallet_name: CallName,
+	/// The name of the call, `None` for all calls of the pallet.
+	pub call_name: Option<CallName>,
+}
+
+impl CallPolicy {
+	/// Whether the described call matches the policy.
+	pub fn matches(&self, description: &CallDescription) -> bool {
+		self.pallet_name[..] == description.pallet_name[..] &&
+			self.call_name
+				.as_ref()
+				.map_or(true, |call_name| call_name[..] == description.call_name[..])
+	}
+}
+
+/// A sponsor pays the fees of the meta-calls naming it within the limits of its paymaster.
+///
+/// The `budget` is reserved from the sponsor account, so the paymaster can always pay what it
+/// promised, the fees are paid from it.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Paymaster<Balance, BlockNumber, AllowedCalls> {
+	/// The fees the paymaster can still pay, the service fee included.
+	pub budget: Balance,
+	/// The calls the paymaster pays for, empty for all calls.
+	pub allowed_calls: AllowedCalls,
+	/// The fees the paymaster pays for a single user at most, `None` for no limit.
+	pub user_quota: Option<Balance>,
+	/// The paymaster stops paying after this block, `None` for never.
+	pub expires_at: Option<BlockNumber>,
+	/// The deposit reserved from the sponsor for this paymaster and its users in `PaymasterSpent`.
+	pub deposit: Balance,
+	/// The number of users in `PaymasterSpent`.
+	pub users: u32,
+}
+
+pub type PaymasterOf<T> = Paymaster<
+	BalanceOf<T>,
+	frame_system::pallet_prelude::BlockNumberFor<T>,
+	BoundedVec<CallPolicy, <T as Config>::MaxPaymasterCalls>,
+>;
+
+/// The longevity of a meta-call in the pool if its validity window has no upper bound.
+pub const DEFAULT_META_CALL_LONGEVITY: u64 = 5;
+
+/// The maximum number of entries of the past rate limit and free quota periods removed in a
+/// block.
+pub const MAX_META_CALL_RATE_CLEANUP: u32 = 512;
+
 pub enum Secp256K1PublicKeyForm {
 	Compressed,
 	Uncompressed,
@@ -85,6 +385,12 @@ pub trait AddressConversion<AccountId>: Sized {
 	const SECP256K1_PUBLIC_KEY_FORM: Secp256K1PublicKeyForm;
 
 	fn try_convert(evm_public_key: &[u8]) -> Option<AccountId>;
+
+	/// Converts from the EVM address, only possible if the account is derived from the address
+	/// rather than the public key.
+	fn try_convert_address(_evm_address: &sp_core::H160) -> Option<AccountId> {
+		None
+	}
 }
 
 pub struct SubstrateAddressConverter;
@@ -103,21 +409,119 @@ impl AddressConversion<AccountId32> for EvmTransparentConverter {
 	fn try_convert(evm_public_key: &[u8]) -> Option<AccountId32> {
 		let h32 = sp_core::H256(sp_io::hashing::keccak_256(evm_public_key));
 		let h20 = sp_core::H160::from(h32);
+
+		Self::try_convert_address(&h20)
+	}
+
+	fn try_convert_address(evm_address: &sp_core::H160) -> Option<AccountId32> {
 		let postfix = b"@evm_address";
 
 		let mut raw_account: AccountId32Bytes = [0; 32];
-		raw_account[..20].copy_from_slice(h20.as_bytes());
+		raw_account[..20].copy_from_slice(evm_address.as_bytes());
 		raw_account[20..].copy_from_slice(postfix);
 
 		Some(AccountId32::from(raw_account))
 	}
 }
 
+/// The EIP-712 domain parameters which can be updated on-chain, the `salt` is always taken from
+/// `Config::EIP712Salt`.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, Serialize, Deserialize)]
+pub struct EIP712DomainParams {
+	pub name: Vec<u8>,
+	pub version: Vec<u8>,
+	pub chain_id: EIP712ChainID,
+	pub verifying_contract: EIP712VerifyingContractAddress,
+}
+
+impl EIP712DomainParams {
+	/// Computes the domain separator.
+	pub fn separator(&self, salt: Option<EIP712Salt>) -> [u8; 32] {
+		crate::eip712::EIP712Domain {
+			name: self.name.clone(),
+			version: self.version.clone(),
+			chain_id: self.chain_id,
+			verifying_contract: self.verifying_contract,
+			salt,
+		}
+		.separator()
+	}
+}
+
+/// Everything besides the message a client needs to build the typed data.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct TypedDataContext {
+	/// The active EIP-712 domain.
+	pub domain: EIP712DomainParams,
+	/// The salt of the EIP-712 domain.
+	pub salt: Option<EIP712Salt>,
+	/// The SS58 prefix `who` is formatted with.
+	pub ss58_prefix: u16,
+	/// The `genesisHash` of the v2 typed data.
+	pub genesis_hash: Vec<u8>,
+	/// The `specVersion` of the v2 typed data.
+	pub spec_version: u32,
+	/// The `txVersion` of the v2 typed data.
+	pub transaction_version: u32,
+}
+
+/// The EIP-712 primary type name and the members of a call template, see `CallTemplates`.
+///
+/// The names are UTF-8 strings.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct TemplateMembers {
+	/// The primary type name, e.g. `Transfer`.
+	pub primary_type: Vec<u8>,
+	/// The names and the values of the members, without those of `TEMPLATE_TYPE_SUFFIX`.
+	pub members: Vec<(Vec<u8>, TemplateValue)>,
+}
+
+/// A meta-call of a `meta_call_bundle`, signed as the `SubstrateCall` v2 typed data.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MetaCallItem<AccountId, Call, Balance, BlockNumber> {
+	pub who: AccountId,
+	pub call: Call,
+	pub nonce: Nonce,
+	pub signature: EIP712Signature,
+	pub terms: MetaCallTerms<Balance, BlockNumber>,
+}
+
+pub type MetaCallItemOf<T> = MetaCallItem<
+	<T as frame_system::Config>::AccountId,
+	<T as Config>::RuntimeCall,
+	PaymentBalanceOf<T>,
+	frame_system::pallet_prelude::BlockNumberFor<T>,
+>;
+
+/// Who pays the fees of a meta-call.
+enum FeePayer<'a, AccountId> {
+	/// The signer pays.
+	Signer,
+	/// The paymaster of the sponsor pays for the described call.
+	Paymaster(&'a AccountId, &'a CallDescription),
+	/// The meta-call is covered by the free quota of the signer.
+	FreeQuota,
+}
+
+/// Derives the EIP-712 domain salt from the genesis block hash, so chains share the same chain ID
+/// can still tell their signatures apart.
+pub struct GenesisHashSalt<T>(core::marker::PhantomData<T>);
+impl<T: frame_system::Config> frame_support::traits::Get<Option<EIP712Salt>> for GenesisHashSalt<T> {
+	fn get() -> Option<EIP712Salt> {
+		let genesis_hash = frame_system::Pallet::<T>::block_hash(
+			frame_system::pallet_prelude::BlockNumberFor::<T>::zero(),
+		);
+		let genesis_hash = genesis_hash.as_ref();
+		Some(genesis_hash.try_into().unwrap_or_else(|_| sp_io::hashing::keccak_256(genesis_hash)))
+	}
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
 	use frame_support::{pallet_prelude::*, traits::OnUnbalanced};
 	use frame_system::pallet_prelude::*;
+	use sp_runtime::{traits::Zero, SaturatedConversion, Saturating};
 
 	#[pallet::pallet]
 	pub struct Pallet<T>(_);
@@ -138,10 +542,13 @@ pub mod pallet {
 			+ codec::Decode
 			+ codec::Encode
 			+ scale_info::TypeInfo
+			+ From<Call<Self>>
 			+ IsType<<Self as frame_system::Config>::RuntimeCall>;
 
-		/// The system's currency for payment.
-		type Currency: InspectFungible<Self::AccountId> + Currency<Self::AccountId>;
+		/// The system's currency for payment, the paymasters reserve their budget and deposits.
+		type Currency: InspectFungible<Self::AccountId>
+			+ Currency<Self::AccountId>
+			+ ReservableCurrency<Self::AccountId>;
 
 		type AddressConverter: AddressConversion<Self::AccountId>;
 
@@ -150,7 +557,88 @@ pub mod pallet {
 
 		type OnUnbalancedForServiceFee: OnUnbalanced<NegativeImbalanceOf<Self>>;
 
-		type CallFilter: Contains<<Self as frame_system::Config>::RuntimeCall>;
+		/// Filters the calls of meta-calls, see `MetaCallFilter`.
+		type CallFilter: MetaCallFilter<
+			Self::AccountId,
+			<Self as frame_system::Config>::RuntimeCall,
+		>;
+
+		/// Describes the call in the v2 typed data, see `CallMetadataDescriber`.
+		type CallDescriber: CallDescriber<<Self as Config>::RuntimeCall>;
+
+		/// The well-known calls which can be signed as dedicated EIP-712 primary types, `()` for
+		/// none.
+		type CallTemplates: CallTemplates<<Self as Config>::RuntimeCall>;
+
+		/// The maximum number of calls in a `meta_batch_call`.
+		#[pallet::constant]
+		type MaxBatchCalls: Get<u32>;
+
+		/// The maximum number of `CallPolicy` of a paymaster.
+		#[pallet::constant]
+		type MaxPaymasterCalls: Get<u32>;
+
+		/// The deposit reserved from a sponsor for its paymaster.
+		#[pallet::constant]
+		type PaymasterDeposit: Get<BalanceOf<Self>>;
+
+		/// The deposit reserved from a sponsor for every user tracked in `PaymasterSpent`.
+		#[pallet::constant]
+		type PaymasterUserDeposit: Get<BalanceOf<Self>>;
+
+		/// The maximum number of users a paymaster with a `user_quota` tracks in
+		/// `PaymasterSpent`, it doesn't sponsor more users.
+		#[pallet::constant]
+		type MaxPaymasterUsers: Get<u32>;
+
+		/// The share of the service fee paid to the relayer submitting a meta-call, capped by the
+		/// `max_relayer_fee` signed in the v2 typed data.
+		#[pallet::constant]
+		type RelayerServiceFeeShare: Get<Perbill>;
+
+		/// The maximum number of items in a `meta_call_bundle`.
+		#[pallet::constant]
+		type MaxBundleItems: Get<u32>;
+
+		/// The calls which can be dispatched by the free quota, so fresh accounts can onboard
+		/// without a relayer.
+		type FreeCallFilter: Contains<<Self as frame_system::Config>::RuntimeCall>;
+
+		/// The number of free meta-calls of an account in a period, zero disables the quota.
+		#[pallet::constant]
+		type FreeCallsPerPeriod: Get<u32>;
+
+		/// The number of free meta-calls of all accounts in a period, so the free quota can't grow
+		/// the state without bound.
+		#[pallet::constant]
+		type MaxFreeCallsPerPeriod: Get<u32>;
+
+		/// The length of a period of the free quota in blocks, zero disables the quota.
+		#[pallet::constant]
+		type FreeCallPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Only accounts whose balance is below this threshold have the free quota.
+		#[pallet::constant]
+		type FreeCallBalanceThreshold: Get<BalanceOf<Self>>;
+
+		/// The maximum number of unsigned meta-calls of an account included in a
+		/// `MetaCallRatePeriod`, zero disables the limit.
+		#[pallet::constant]
+		type MaxMetaCallsPerPeriod: Get<u32>;
+
+		/// The number of nonce lanes of an account, the nonce keys must be below it so an account
+		/// can't spread its pending meta-calls over unbounded lanes.
+		#[pallet::constant]
+		type MaxNonceLanes: Get<u32>;
+
+		/// The length of a period of the meta-call rate limit in blocks.
+		#[pallet::constant]
+		type MetaCallRatePeriod: Get<BlockNumberFor<Self>>;
+
+		/// The maximum share of the block weight and of the normal block length the unsigned
+		/// meta-calls can take in a block.
+		#[pallet::constant]
+		type MaxMetaCallBlockShare: Get<Perbill>;
 
 		#[pallet::constant]
 		type EIP712Name: Get<Vec<u8>>;
@@ -161,9 +649,28 @@ pub mod pallet {
 		#[pallet::constant]
 		type EIP712ChainID: Get<EIP712ChainID>;
 
+		/// Chain IDs accepted besides the one of the EIP-712 domain.
+		///
+		/// Wallets refuse to sign if the domain's chain ID mismatches the connected network,
+		/// so users can sign without switching the network. Every entry costs an extra signature
+		/// recovery for a mismatched signature, so keep it short.
+		#[pallet::constant]
+		type EIP712ExtraChainIDs: Get<Vec<EIP712ChainID>>;
+
 		#[pallet::constant]
 		type EIP712VerifyingContractAddress: Get<EIP712VerifyingContractAddress>;
 
+		/// The optional salt of the EIP-712 domain, see `GenesisHashSalt`.
+		///
+		/// It isn't a constant because it may be derived from the chain state.
+		type EIP712Salt: Get<Option<EIP712Salt>>;
+
+		/// The origin which may update the EIP-712 domain.
+		type EIP712DomainOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The origin which may pause the meta-calls and freeze accounts.
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
 		/// Type representing the weight of this pallet
 		type WeightInfo: WeightInfo;
 	}
@@ -187,6 +694,77 @@ pub mod pallet {
 			who: T::AccountId,
 			call_result: DispatchResultWithPostInfo,
 		},
+		/// A call of a `meta_batch_call` is done.
+		BatchCallDone {
+			who: T::AccountId,
+			index: u32,
+			call_result: DispatchResultWithPostInfo,
+		},
+		/// `sponsor` has registered or updated its paymaster.
+		PaymasterRegistered {
+			sponsor: T::AccountId,
+			paymaster: PaymasterOf<T>,
+		},
+		/// `sponsor` has unregistered its paymaster.
+		PaymasterUnregistered {
+			sponsor: T::AccountId,
+		},
+		/// The fees of a meta-call of `who` are paid by `sponsor`.
+		MetaCallSponsored {
+			who: T::AccountId,
+			sponsor: T::AccountId,
+		},
+		/// The meta-calls of `who` in the lane `nonce_key` are cancelled, the next nonce of the
+		/// lane is `next_nonce`, `Nonce::MAX` if the lane is closed.
+		MetaNonceCancelled {
+			who: T::AccountId,
+			nonce_key: NonceKey,
+			next_nonce: Nonce,
+		},
+		/// The relayer submitting a meta-call of `who` is paid `amount` of the service fee.
+		RelayerFeePaid {
+			relayer: T::AccountId,
+			who: T::AccountId,
+			amount: BalanceOf<T>,
+		},
+		/// The item `index` of a `meta_call_bundle` is done, a failed item is reverted.
+		BundleItemDone {
+			who: T::AccountId,
+			index: u32,
+			result: DispatchResult,
+		},
+		/// A meta-call of `who` is covered by the free quota, `remaining` free calls are left in
+		/// the period.
+		FreeCallUsed {
+			who: T::AccountId,
+			remaining: u32,
+		},
+		/// An atomic `meta_batch_call` is reverted because the call `index` failed.
+		BatchInterrupted {
+			who: T::AccountId,
+			index: u32,
+			error: DispatchError,
+		},
+		/// The EIP-712 domain has been updated, the previous one is still accepted until
+		/// `previous_valid_until` if it is set.
+		EIP712DomainUpdated {
+			domain: EIP712DomainParams,
+			previous_valid_until: Option<BlockNumberFor<T>>,
+		},
+		/// The meta-calls are paused, they are unpaused at `unpause_at` if it is set.
+		MetaCallsPaused {
+			unpause_at: Option<BlockNumberFor<T>>,
+		},
+		/// The meta-calls are unpaused.
+		MetaCallsUnpaused,
+		/// The meta-calls of `who` are frozen.
+		AccountFrozen {
+			who: T::AccountId,
+		},
+		/// The meta-calls of `who` are no longer frozen.
+		AccountThawed {
+			who: T::AccountId,
+		},
 	}
 
 	// Errors inform users that something went wrong.
@@ -195,182 +773,386 @@ pub mod pallet {
 		Unexpected,
 		NonceError,
 		PaymentError,
+		/// The legacy typed data doesn't cover the tip, so it must be zero.
+		UnsignedTip,
+		/// The total fee exceeds the `max_fee` signed by the user.
+		MaxFeeExceeded,
+		/// The validity window signed by the user has passed.
+		MetaCallExpired,
+		/// The validity window signed by the user hasn't started yet.
+		MetaCallNotYetValid,
+		/// The call template can't be converted into a call.
+		InvalidCallTemplate,
+		/// The batch has no calls.
+		EmptyBatch,
+		/// The batch has more than `MaxBatchCalls` calls.
+		TooManyCalls,
+		/// The signature of a `sponsored_meta_call` isn't made by `who`.
+		InvalidSignature,
+		/// The bundle has no items.
+		EmptyBundle,
+		/// The bundle has more than `MaxBundleItems` items.
+		TooManyBundleItems,
+		/// The named sponsor has no paymaster.
+		PaymasterNotFound,
+		/// The paymaster has expired.
+		PaymasterExpired,
+		/// The paymaster doesn't pay for the call.
+		CallNotSponsored,
+		/// The budget of the paymaster can't cover the fees.
+		PaymasterBudgetExhausted,
+		/// The paymaster has paid the quota of the user.
+		PaymasterQuotaExceeded,
+		/// The meta-calls are paused.
+		MetaCallsPaused,
+		/// The meta-calls of the account are frozen.
+		AccountFrozen,
+		/// The meta-calls aren't paused.
+		NotPaused,
+		/// The account isn't frozen.
+		AccountNotFrozen,
+		/// The block to unpause the meta-calls at has passed.
+		InvalidUnpauseBlock,
+		/// The nonce key isn't below `MaxNonceLanes`.
+		InvalidNonceLane,
 	}
 
 	#[pallet::storage]
 	pub(crate) type AccountNonce<T: Config> =
 		StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
 
-	#[pallet::validate_unsigned]
-	impl<T: Config> ValidateUnsigned for Pallet<T>
-	where
-		PaymentBalanceOf<T>: FixedPointOperand,
-		BalanceOf<T>: FixedPointOperand,
-		<T as frame_system::Config>::RuntimeCall:
-			Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
-		<T as frame_system::Config>::AccountId: From<AccountId32Bytes> + Into<AccountId32Bytes>,
-		T: frame_system::Config<AccountId = AccountId32>,
-	{
-		type Call = Call<T>;
+	/// The nonces of the keyed lanes of the v2 meta-calls, like the keyed nonces of ERC-4337.
+	///
+	/// Lane zero is `AccountNonce`, so it isn't stored here.
+	#[pallet::storage]
+	pub type LaneNonce<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Twox64Concat,
+		NonceKey,
+		Nonce,
+		ValueQuery,
+	>;
+
+	#[pallet::type_value]
+	pub fn DefaultEIP712Domain<T: Config>() -> EIP712DomainParams {
+		EIP712DomainParams {
+			name: T::EIP712Name::get(),
+			version: T::EIP712Version::get(),
+			chain_id: T::EIP712ChainID::get(),
+			verifying_contract: T::EIP712VerifyingContractAddress::get(),
+		}
+	}
 
-		fn validate_unsigned(
-			_source: TransactionSource,
-			unsigned_call: &Self::Call,
-		) -> TransactionValidity {
-			// Only allow `meta_call`
-			let Call::meta_call {
-				ref who,
-				ref call,
-				ref nonce,
-				ref signature,
-				ref tip,
-			} = unsigned_call
-			else {
-				return Err(InvalidTransaction::Call.into())
-			};
+	/// The paymasters, keyed by the sponsor paying the fees.
+	#[pallet::storage]
+	pub type Paymasters<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, PaymasterOf<T>>;
 
-			// Check the signature and get the public key
-			let call_data = <T as Config>::RuntimeCall::encode(call);
-			let message_hash = Self::eip712_message_hash(who.clone(), &call_data, *nonce);
+	/// The fees a paymaster with a `user_quota` has paid for a user, they are kept if the paymaster
+	/// is re-registered so the quotas can't be reset, and removed when it is unregistered.
+	#[pallet::storage]
+	pub type PaymasterSpent<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		BalanceOf<T>,
+		ValueQuery,
+	>;
+
+	/// The number of free meta-calls an account has used in a period of the free quota, keyed by
+	/// the index of the period. The quota regenerates once a new period starts, `on_initialize`
+	/// removes the past periods.
+	#[pallet::storage]
+	pub type FreeCallsUsed<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		ValueQuery,
+	>;
+
+	/// The period of the free quota and the number of free meta-calls of all accounts in it.
+	#[pallet::storage]
+	pub type FreeCallsInPeriod<T: Config> = StorageValue<_, (BlockNumberFor<T>, u32), ValueQuery>;
 
-			let Ok(recovered_public_key) = (match <T as Config>::AddressConverter::SECP256K1_PUBLIC_KEY_FORM {
-				Secp256K1PublicKeyForm::Compressed => {
-					sp_io::crypto::secp256k1_ecdsa_recover_compressed(signature, &message_hash)
-						.map(|i| i.to_vec())
-				},
-				Secp256K1PublicKeyForm::Uncompressed => {
-					sp_io::crypto::secp256k1_ecdsa_recover(signature, &message_hash)
-						.map(|i| i.to_vec())
-				}
-			}) else {
-				return Err(InvalidTransaction::Call.into())
-			};
+	/// The oldest period of the free quota which may still have entries in `FreeCallsUsed`.
+	#[pallet::storage]
+	pub(crate) type OldestFreeCallPeriod<T: Config> =
+		StorageValue<_, BlockNumberFor<T>, OptionQuery>;
 
-			// Deserialize the actual caller
-			let Some(decoded_account) =
-				<T as Config>::AddressConverter::try_convert(&recovered_public_key) else {
-				return Err(InvalidTransaction::Call.into())
-			};
-			if who != &decoded_account {
-				return Err(InvalidTransaction::BadSigner.into())
-			}
+	/// The active EIP-712 domain, it falls back to the `Config` constants if not set.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type CurrentEIP712Domain<T: Config> =
+		StorageValue<_, EIP712DomainParams, ValueQuery, DefaultEIP712Domain<T>>;
 
-			// Skip frame_system::CheckNonZeroSender
-			// Skip frame_system::CheckSpecVersion<Runtime>
-			// Skip frame_system::CheckTxVersion<Runtime>
-			// Skip frame_system::CheckGenesis<Runtime>
-			// Skip frame_system::CheckEra<Runtime>
-
-			// frame_system::CheckNonce<Runtime>
-			let account_nonce = AccountNonce::<T>::get(who);
-			if nonce < &account_nonce {
-				return Err(InvalidTransaction::Stale.into())
-			}
-			let provides = (who, nonce).encode();
-			let requires = if &account_nonce < nonce && nonce > &0u64 {
-				Some((who, nonce - 1).encode())
-			} else {
-				None
-			};
-			if nonce != &account_nonce {
-				return Err(if nonce < &account_nonce {
-					InvalidTransaction::Stale
-				} else {
-					InvalidTransaction::Future
-				}
-				.into())
-			}
+	/// The replaced EIP-712 domain and the last block it is still accepted.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type PreviousEIP712Domain<T: Config> =
+		StorageValue<_, (EIP712DomainParams, BlockNumberFor<T>), OptionQuery>;
 
-			// Skip frame_system::CheckWeight<Runtime>
-			// it has implemented `validate_unsigned` and `pre_dispatch_unsigned`, we don't need to
-			// do the validate here.
+	/// Whether the meta-calls are paused, e.g. when a bug is found in the signature verification
+	/// or the address converter.
+	#[pallet::storage]
+	pub type Paused<T: Config> = StorageValue<_, bool, ValueQuery>;
 
-			// pallet_transaction_payment::ChargeTransactionPayment<Runtime>
-			let tip = tip.unwrap_or(0u32.into());
-			let len = call.encoded_size();
-			let info = call.get_dispatch_info();
-			// We shall get the same `fee` later
-			let est_fee =
-				pallet_transaction_payment::Pallet::<T>::compute_fee(len as u32, &info, tip);
-			// TODO: Need check this work with assets-payment
-			// We don't withdraw the fee here, because we can't cache the imbalance
-			// Instead, we check the account has enough fee
-			// I think this is a hack, or the type can't match
-			let est_fee = est_fee.saturated_into::<u128>();
-			// We can't get the actual size of the meta-tx itself,
-			// so we have to introducing service fee.
-			let service_fee = T::ServiceFee::get().saturated_into::<u128>();
-			let usable_balance_for_fees =
-				T::Currency::reducible_balance(who, Preservation::Preserve, Fortitude::Polite)
-					.saturated_into::<u128>();
-			if est_fee.saturating_add(service_fee) > usable_balance_for_fees {
-				return Err(InvalidTransaction::Payment.into())
-			}
+	/// The block `on_initialize` unpauses the meta-calls at.
+	#[pallet::storage]
+	pub type UnpauseAt<T: Config> = StorageValue<_, BlockNumberFor<T>, OptionQuery>;
 
-			// Calculate priority
-			// Cheat from `get_priority` in frame/transaction-payment/src/lib.rs
-			use frame_support::traits::Defensive;
-			use sp_runtime::{traits::One, SaturatedConversion, Saturating};
-			// Calculate how many such extrinsics we could fit into an empty block and take the
-			// limiting factor.
-			let max_block_weight = <T as frame_system::Config>::BlockWeights::get().max_block;
-			let max_block_length =
-				*<T as frame_system::Config>::BlockLength::get().max.get(info.class) as u64;
+	/// The accounts whose meta-calls are frozen.
+	#[pallet::storage]
+	pub type FrozenAccounts<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, ()>;
 
-			// bounded_weight is used as a divisor later so we keep it non-zero.
-			let bounded_weight = info.weight.max(Weight::from_parts(1, 1)).min(max_block_weight);
-			let bounded_length = (len as u64).clamp(1, max_block_length);
+	/// The number of unsigned meta-calls of an account included in a rate limit period, keyed by
+	/// the index of the period. `on_initialize` removes the past periods.
+	#[pallet::storage]
+	pub type MetaCallsInPeriod<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		ValueQuery,
+	>;
+
+	/// The oldest rate limit period which may still have entries in `MetaCallsInPeriod`.
+	#[pallet::storage]
+	pub(crate) type OldestMetaCallPeriod<T: Config> =
+		StorageValue<_, BlockNumberFor<T>, OptionQuery>;
 
-			// returns the scarce resource, i.e. the one that is limiting the number of
-			// transactions.
-			let max_tx_per_block_weight = max_block_weight
-				.checked_div_per_component(&bounded_weight)
-				.defensive_proof("bounded_weight is non-zero; qed")
-				.unwrap_or(1);
-			let max_tx_per_block_length = max_block_length / bounded_length;
-			// Given our current knowledge this value is going to be in a reasonable range - i.e.
-			// less than 10^9 (2^30), so multiplying by the `tip` value is unlikely to overflow the
-			// balance type. We still use saturating ops obviously, but the point is to end up with
-			// some `priority` distribution instead of having all transactions saturate the
-			// priority.
-			let max_tx_per_block = max_tx_per_block_length
-				.min(max_tx_per_block_weight)
-				.saturated_into::<PaymentBalanceOf<T>>();
-			let max_reward = |val: PaymentBalanceOf<T>| val.saturating_mul(max_tx_per_block);
+	/// The weight and the length taken by the unsigned meta-calls in the current block, it is
+	/// removed in `on_finalize`.
+	#[pallet::storage]
+	pub(crate) type MetaCallBlockUsage<T: Config> = StorageValue<_, (Weight, u32), ValueQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// Overrides the EIP-712 domain `Config` constants.
+		pub eip712_domain: Option<EIP712DomainParams>,
+		#[serde(skip)]
+		pub _config: core::marker::PhantomData<T>,
+	}
 
-			// To distribute no-tip transactions a little bit, we increase the tip value by one.
-			// This means that given two transactions without a tip, smaller one will be preferred.
-			let tip = tip.saturating_add(One::one());
-			let scaled_tip = max_reward(tip);
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			if let Some(domain) = &self.eip712_domain {
+				CurrentEIP712Domain::<T>::put(domain);
+			}
+		}
+	}
 
-			let priority = scaled_tip.saturated_into::<TransactionPriority>();
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let mut weight = T::DbWeight::get().reads(1);
+			if UnpauseAt::<T>::get().map_or(false, |unpause_at| n >= unpause_at) {
+				Self::do_unpause();
+				weight.saturating_accrue(T::DbWeight::get().writes(2));
+			}
+			// `MetaCallBlockUsage` is removed in `on_finalize`
+			weight
+				.saturating_add(Self::clean_up_meta_call_periods())
+				.saturating_add(Self::clean_up_free_call_periods())
+				.saturating_add(T::DbWeight::get().writes(1))
+		}
 
-			// Finish the validation
-			let valid_transaction_builder = ValidTransaction::with_tag_prefix("EVMAccountMapping")
-				.priority(priority)
-				.and_provides(provides)
-				.longevity(5)
-				.propagate(true);
-			let Some(requires) = requires else { return valid_transaction_builder.build() };
-			valid_transaction_builder.and_requires(requires).build()
+		fn on_finalize(_n: BlockNumberFor<T>) {
+			MetaCallBlockUsage::<T>::kill();
 		}
 	}
 
-	#[pallet::call]
-	impl<T: Config> Pallet<T>
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T>
 	where
 		PaymentBalanceOf<T>: FixedPointOperand,
 		BalanceOf<T>: FixedPointOperand,
 		<T as frame_system::Config>::RuntimeCall:
 			Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
-		T: frame_system::Config<AccountId = sp_runtime::AccountId32>,
+		<T as frame_system::Config>::AccountId: From<AccountId32Bytes> + Into<AccountId32Bytes>,
+		T: frame_system::Config<AccountId = AccountId32>,
+	{
+		type Call = Call<T>;
+
+		/// Runs all checks of `validate_unsigned` again when the meta-call is included in a block,
+		/// so a block author can't include a meta-call with a bad signature or nonce, or whose
+		/// payer can't pay the fees. Then the meta-call is counted against the rate limits.
+		fn pre_dispatch(unsigned_call: &Self::Call) -> Result<(), TransactionValidityError> {
+			Self::validate_unsigned(TransactionSource::InBlock, unsigned_call)?;
+			Self::note_meta_call_in_block(unsigned_call)
+		}
+
+		fn validate_unsigned(
+			_source: TransactionSource,
+			unsigned_call: &Self::Call,
+		) -> TransactionValidity {
+			if let Some((who, ..)) = Self::unsigned_meta_call_sender(unsigned_call) {
+				Self::ensure_meta_calls_enabled(who).map_err(|error| match error {
+					Error::<T>::AccountFrozen => MetaCallValidityError::AccountFrozen,
+					_ => MetaCallValidityError::Paused,
+				})?;
+			}
+			Self::check_meta_call_limits(unsigned_call)?;
+
+			// Only allow `meta_call`, `meta_call_v2`, `meta_call_template`, `meta_batch_call` and
+			// `meta_cancel_nonce`
+			match unsigned_call {
+				Call::meta_call { who, call, nonce, signature, tip } => {
+					// The legacy typed data doesn't cover the tip, so anyone could inflate it
+					if tip.map_or(false, |tip| !tip.is_zero()) {
+						return Err(MetaCallValidityError::UnsignedTip.into())
+					}
+					Self::validate_meta_call(who, call, *nonce, signature, None)
+				},
+				Call::meta_call_v2 { who, call, nonce, signature, terms } =>
+					Self::validate_meta_call(who, call, *nonce, signature, Some(terms)),
+				Call::meta_call_template { who, template, nonce, valid_until, signature } => {
+					let Some(call) = T::CallTemplates::into_call(template.clone()) else {
+						return Err(MetaCallValidityError::InvalidCallTemplate.into())
+					};
+					Self::ensure_calls_allowed(who, [&call], 0, *nonce)?;
+					let current_block_number = frame_system::Pallet::<T>::block_number();
+					if !valid_until.is_zero() && current_block_number > *valid_until {
+						return Err(InvalidTransaction::Stale.into())
+					}
+					let struct_hash =
+						Self::eip712_template_struct_hash(template, *nonce, *valid_until);
+					let mut validity = Self::validate_signed_meta_call(
+						who,
+						call.encoded_size(),
+						&call.get_dispatch_info(),
+						*nonce,
+						signature,
+						&struct_hash,
+						None,
+						Self::signer_fee_payer(who, [&call], None),
+					)?;
+					if !valid_until.is_zero() {
+						validity.longevity = valid_until
+							.saturating_sub(current_block_number)
+							.saturated_into::<u64>()
+							.max(1);
+					}
+					Ok(validity)
+				},
+				Call::meta_batch_call {
+					who,
+					calls,
+					nonce,
+					signature,
+					atomic,
+					max_fee,
+					valid_until,
+				} => {
+					if calls.is_empty() || calls.len() > T::MaxBatchCalls::get() as usize {
+						return Err(MetaCallValidityError::InvalidBatchSize.into())
+					}
+					Self::ensure_calls_allowed(who, calls, 0, *nonce)?;
+					let encoded_calls =
+						calls.iter().map(|call| call.encode()).collect::<Vec<_>>();
+					let struct_hash = Self::eip712_batch_struct_hash(
+						who.clone(),
+						&encoded_calls,
+						*nonce,
+						*atomic,
+						*max_fee,
+						*valid_until,
+					);
+					let terms = Self::batch_terms(*max_fee, *valid_until);
+					Self::validate_signed_meta_call(
+						who,
+						calls.encoded_size(),
+						&Self::batch_dispatch_info(calls),
+						*nonce,
+						signature,
+						&struct_hash,
+						Some(&terms),
+						Self::signer_fee_payer(who, calls, Some(&terms)),
+					)
+				},
+				Call::meta_cancel_nonce { who, nonce_key, up_to, valid_until, signature } => {
+					let struct_hash = Self::eip712_cancel_struct_hash(
+						who.clone(),
+						*nonce_key,
+						*up_to,
+						*valid_until,
+					);
+					Self::verify_signer(who, signature, &struct_hash)?;
+
+					let current_block_number = frame_system::Pallet::<T>::block_number();
+					let mut longevity = DEFAULT_META_CALL_LONGEVITY;
+					if !valid_until.is_zero() {
+						if current_block_number > *valid_until {
+							return Err(InvalidTransaction::Stale.into())
+						}
+						longevity = valid_until
+							.saturating_sub(current_block_number)
+							.saturated_into::<u64>()
+							.max(1);
+					}
+
+					let next_nonce = Self::lane_nonce(who, *nonce_key);
+					if *up_to < next_nonce || next_nonce == Nonce::MAX {
+						return Err(InvalidTransaction::Stale.into())
+					}
+					if !T::CallFilter::can_cancel(who, *nonce_key, next_nonce, *up_to) {
+						return Err(MetaCallValidityError::CallFiltered.into())
+					}
+					// It pays the transaction fee and the service fee like a meta-call
+					let est_fee = pallet_transaction_payment::Pallet::<T>::compute_fee(
+						unsigned_call.encoded_size() as u32,
+						&unsigned_call.get_dispatch_info(),
+						Zero::zero(),
+					);
+					let usable_balance_for_fees =
+						T::Currency::reducible_balance(who, Preservation::Preserve, Fortitude::Polite)
+							.saturated_into::<u128>();
+					if est_fee
+						.saturated_into::<u128>()
+						.saturating_add(T::ServiceFee::get().saturated_into::<u128>()) >
+						usable_balance_for_fees
+					{
+						return Err(MetaCallValidityError::InsufficientBalanceForFees.into())
+					}
+
+					ValidTransaction::with_tag_prefix("EVMAccountMappingCancel")
+						.and_provides((who, nonce_key, up_to))
+						.longevity(longevity)
+						.propagate(true)
+						.build()
+				},
+				_ => Err(MetaCallValidityError::UnsupportedCall.into()),
+			}
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T>
+	where
+		PaymentBalanceOf<T>: FixedPointOperand,
+		BalanceOf<T>: FixedPointOperand,
+		<T as frame_system::Config>::RuntimeCall:
+			Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+		T: frame_system::Config<AccountId = sp_runtime::AccountId32>,
 	{
 		/// Meta-transaction from EVM compatible chains
+		///
+		/// The legacy `SubstrateCall` typed data doesn't cover the tip, so the `tip` must be
+		/// `None` or zero, use `meta_call_v2` to pay a tip.
 		#[pallet::call_index(0)]
 		#[pallet::weight({
 			let di = call.get_dispatch_info();
 			(
-				T::WeightInfo::meta_call().saturating_add(di.weight),
+				Pallet::<T>::meta_call_weight(None).saturating_add(di.weight),
 				di.class
 			)
 		})]
@@ -385,123 +1167,1862 @@ pub mod pallet {
 			// This is an unsigned transaction
 			ensure_none(origin)?;
 
-			// We don't need to re-validate the signature here,
-			// because it already validated in `validate_unsigned` stage,
-			// and it should no way to skip.
-			// TODO: Confirm this.
+			ensure!(tip.map_or(true, |tip| tip.is_zero()), Error::<T>::UnsignedTip);
 
-			// It is possible that an account passed `validate_unsigned` check,
-			// but for some reason, its balance isn't enough for the service fee.
-			use frame_support::traits::tokens::{WithdrawReasons, ExistenceRequirement};
-			// NOTE: it is possible that the account doesn't have enough fee, which is a vulnerable.
-			let withdrawn = T::Currency::withdraw(
-				&who,
-				T::ServiceFee::get(),
-				WithdrawReasons::FEE,
-				ExistenceRequirement::KeepAlive
-			).map_err(|_err| Error::<T>::PaymentError)?;
-			let withdrawn_fee = withdrawn.peek();
-			T::OnUnbalancedForServiceFee::on_unbalanced(withdrawn);
-			Self::deposit_event(Event::ServiceFeePaid {
-				who: who.clone(),
-				actual_fee: withdrawn_fee,
-				expected_fee: T::ServiceFee::get(),
-			});
+			Self::do_meta_call(who, call, nonce, None, None)
+		}
 
-			// Bump the nonce
-			AccountNonce::<T>::try_mutate(&who, |value| {
-				if *value != nonce {
-					return Err(Error::<T>::NonceError)
+		/// Meta-transaction from EVM compatible chains, the signature covers the tip and the
+		/// maximum total fee
+		#[pallet::call_index(1)]
+		#[pallet::weight({
+			let di = call.get_dispatch_info();
+			(
+				Pallet::<T>::meta_call_weight(Some(terms)).saturating_add(di.weight),
+				di.class
+			)
+		})]
+		pub fn meta_call_v2(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			call: Box<<T as Config>::RuntimeCall>,
+			nonce: Nonce,
+			#[allow(unused_variables)] signature: EIP712Signature,
+			terms: MetaCallTermsOf<T>,
+		) -> DispatchResult {
+			// This is an unsigned transaction
+			ensure_none(origin)?;
+
+			Self::do_meta_call(who, call, nonce, Some(terms), None)
+		}
+
+		/// Meta-transaction from EVM compatible chains, signed as the dedicated EIP-712 primary
+		/// type of a call template
+		///
+		/// Like `meta_call`, the tip is zero. The call can't be included after `valid_until`, zero
+		/// means no upper bound.
+		#[pallet::call_index(3)]
+		#[pallet::weight({
+			let di = T::CallTemplates::into_call(template.clone())
+				.map(|call| call.get_dispatch_info())
+				.unwrap_or_default();
+			(
+				Pallet::<T>::meta_call_weight(None).saturating_add(di.weight),
+				di.class
+			)
+		})]
+		pub fn meta_call_template(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			template: TemplateOf<T>,
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+			#[allow(unused_variables)] signature: EIP712Signature,
+		) -> DispatchResult {
+			// This is an unsigned transaction
+			ensure_none(origin)?;
+			ensure!(
+				valid_until.is_zero() || frame_system::Pallet::<T>::block_number() <= valid_until,
+				Error::<T>::MetaCallExpired
+			);
+
+			let call = T::CallTemplates::into_call(template).ok_or(Error::<T>::InvalidCallTemplate)?;
+			Self::do_meta_call(who, Box::new(call), nonce, None, None)
+		}
+
+		/// Meta-transaction from EVM compatible chains, one signature authorizes several calls
+		///
+		/// If `atomic` is set, all calls are reverted once one fails, otherwise the remaining
+		/// calls are still dispatched. The result of every call is in a `BatchCallDone` event.
+		/// The tip is zero, the total fee can't exceed `max_fee` and the batch can't be included
+		/// after `valid_until`, zero means no upper bound.
+		#[pallet::call_index(4)]
+		#[pallet::weight({
+			let di = Pallet::<T>::batch_dispatch_info(calls);
+			(
+				Pallet::<T>::meta_call_weight(None).saturating_add(di.weight),
+				di.class
+			)
+		})]
+		pub fn meta_batch_call(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			calls: Vec<<T as Config>::RuntimeCall>,
+			nonce: Nonce,
+			#[allow(unused_variables)] signature: EIP712Signature,
+			atomic: bool,
+			max_fee: PaymentBalanceOf<T>,
+			valid_until: BlockNumberFor<T>,
+		) -> DispatchResult {
+			// This is an unsigned transaction
+			ensure_none(origin)?;
+
+			ensure!(!calls.is_empty(), Error::<T>::EmptyBatch);
+			ensure!(calls.len() <= T::MaxBatchCalls::get() as usize, Error::<T>::TooManyCalls);
+
+			let len = calls.encoded_size();
+			let info = Self::batch_dispatch_info(&calls);
+			// Only some `OnChargeTransaction` look into the call, the first one stands for the batch
+			let fee_call = calls[0].clone().into();
+			let terms = Self::batch_terms(max_fee, valid_until);
+			let fee_payer = Self::signer_fee_payer(&who, &calls, Some(&terms));
+			let dispatch = |origin: T::RuntimeOrigin| {
+				let mut actual_weight = Weight::zero();
+				let dispatch_all = || -> Result<(), (u32, DispatchError)> {
+					for (index, call) in calls.into_iter().enumerate() {
+						let index = index as u32;
+						let call_info = call.get_dispatch_info();
+						let call_result = call.dispatch(origin.clone());
+						actual_weight.saturating_accrue(frame_support::dispatch::extract_actual_weight(
+							&call_result,
+							&call_info,
+						));
+						let error =
+							call_result.as_ref().err().map(|error_and_info| error_and_info.error);
+						Self::deposit_event(Event::BatchCallDone {
+							who: who.clone(),
+							index,
+							call_result,
+						});
+						if let Some(error) = error.filter(|_| atomic) {
+							return Err((index, error))
+						}
+					}
+					Ok(())
+				};
+				if atomic {
+					let mut interrupted = None;
+					let _ = frame_support::storage::with_storage_layer(|| {
+						dispatch_all().map_err(|(index, error)| {
+							interrupted = Some((index, error));
+							error
+						})
+					});
+					if let Some((index, error)) = interrupted {
+						Self::deposit_event(Event::BatchInterrupted { who: who.clone(), index, error });
+					}
+				} else {
+					let _ = dispatch_all();
 				}
-				*value += 1;
-				Ok(())
-			})?;
+				PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes }
+			};
+			Self::charge_and_dispatch(
+				&who, nonce, Some(terms), fee_payer, None, len, &info, &fee_call, dispatch,
+			)
+		}
+
+		/// Meta-transaction from EVM compatible chains, the signer of the extrinsic (a relayer or
+		/// a dapp backend) pays all fees and the call still runs as `who`
+		///
+		/// There is no service fee, the extrinsic is charged by its length like other signed
+		/// extrinsics. If `sponsor_bound` is set, the user signed the SS58 address of the sponsor,
+		/// otherwise any sponsor may submit it. The call can't be included after `valid_until`,
+		/// zero means no upper bound.
+		#[pallet::call_index(5)]
+		#[pallet::weight({
+			let di = call.get_dispatch_info();
+			(
+				Pallet::<T>::meta_call_weight(None).saturating_add(di.weight),
+				di.class
+			)
+		})]
+		pub fn sponsored_meta_call(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			call: Box<<T as Config>::RuntimeCall>,
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+			signature: EIP712Signature,
+			sponsor_bound: bool,
+		) -> DispatchResultWithPostInfo {
+			let sponsor = ensure_signed(origin)?;
+			ensure!(
+				valid_until.is_zero() || frame_system::Pallet::<T>::block_number() <= valid_until,
+				Error::<T>::MetaCallExpired
+			);
+
+			let call_data = <T as Config>::RuntimeCall::encode(&call);
+			let description = T::CallDescriber::describe(&call);
+			let struct_hash = Self::eip712_sponsored_struct_hash(
+				who.clone(),
+				&call_data,
+				&description,
+				nonce,
+				valid_until,
+				sponsor_bound.then_some(&sponsor),
+			);
+			Self::ensure_signed_meta_call(&who, &struct_hash, &signature, 0, nonce)?;
+
+			ensure!(
+				T::CallFilter::contains(&who, (*call).into_ref(), 0, nonce),
+				frame_system::Error::<T>::CallFiltered
+			);
+			Self::bump_nonce(&who, 0, nonce)?;
 
 			// Call
-			let mut origin: T::RuntimeOrigin = RawOrigin::Signed(who.clone()).into();
-			origin.add_filter(T::CallFilter::contains);
-			let len = call.encoded_size();
 			let info = call.get_dispatch_info();
-			let tip = tip.unwrap_or(0u32.into());
-			let est_fee =
-				pallet_transaction_payment::Pallet::<T>::compute_fee(len as u32, &info, tip);
-			// Add the service fee
-			let already_withdrawn =
-				<PaymentOnChargeTransaction<T> as OnChargeTransaction<T>>::withdraw_fee(
-					&who,
-					&(*call).clone().into(),
-					&info,
-					est_fee,
-					tip,
-				)
-				.map_err(|_err| Error::<T>::PaymentError)?;
+			let call_result = call.dispatch(Self::meta_call_origin(&who, 0, nonce));
+			let call_weight = frame_support::dispatch::extract_actual_weight(&call_result, &info);
+			Self::deposit_event(Event::CallDone { who: who.clone(), call_result });
+			Self::deposit_event(Event::MetaCallSponsored { who, sponsor });
 
-			let call_result = call.dispatch(origin);
-			let post_info = match call_result {
-				Ok(post_info) => post_info,
-				Err(error_and_info) => error_and_info.post_info,
+			Ok(Some(Self::meta_call_weight(None).saturating_add(call_weight)).into())
+		}
+
+		/// Registers or replaces the paymaster of the signer, which pays the fees of the
+		/// meta-calls naming the signer as `paymaster` in the signed terms
+		///
+		/// The `budget` and `PaymasterDeposit` are reserved, the budget of a replaced paymaster is
+		/// released first.
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::register_paymaster())]
+		pub fn register_paymaster(
+			origin: OriginFor<T>,
+			budget: BalanceOf<T>,
+			allowed_calls: BoundedVec<CallPolicy, T::MaxPaymasterCalls>,
+			user_quota: Option<BalanceOf<T>>,
+			expires_at: Option<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let sponsor = ensure_signed(origin)?;
+
+			let (deposit, users) = match Paymasters::<T>::get(&sponsor) {
+				Some(replaced) => {
+					T::Currency::unreserve(&sponsor, replaced.budget);
+					(replaced.deposit, replaced.users)
+				},
+				None => {
+					let deposit = T::PaymasterDeposit::get();
+					T::Currency::reserve(&sponsor, deposit)?;
+					(deposit, 0)
+				},
 			};
-			// Deposit the call's result
-			Self::deposit_event(Event::CallDone { who: who.clone(), call_result });
+			T::Currency::reserve(&sponsor, budget)?;
 
-			let actual_fee = pallet_transaction_payment::Pallet::<T>::compute_actual_fee(
-				len as u32, &info, &post_info, tip,
-			);
-			// frame/transaction-payment/src/payment.rs
-			<PaymentOnChargeTransaction<T> as OnChargeTransaction<T>>::correct_and_deposit_fee(
+			let paymaster =
+				Paymaster { budget, allowed_calls, user_quota, expires_at, deposit, users };
+			Paymasters::<T>::insert(&sponsor, &paymaster);
+
+			Self::deposit_event(Event::PaymasterRegistered { sponsor, paymaster });
+			Ok(())
+		}
+
+		/// Cancels the meta-calls of the signer in the lane `nonce_key` with a nonce up to `up_to`,
+		/// `Nonce::MAX` closes the lane for good
+		///
+		/// Relayers can drop their queued copies once they see the `MetaNonceCancelled` event.
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::cancel_meta_nonce())]
+		pub fn cancel_meta_nonce(
+			origin: OriginFor<T>,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::do_cancel_meta_nonce(who, nonce_key, up_to)
+		}
+
+		/// Like `cancel_meta_nonce`, signed as the `CancelMetaNonce` typed data so the account
+		/// doesn't need to sign a Substrate extrinsic
+		///
+		/// The account is charged like a meta-call, the cancellation takes the next nonce of the
+		/// lane. It can't be included after `valid_until`, zero means no upper bound.
+		#[pallet::call_index(11)]
+		#[pallet::weight(
+			Pallet::<T>::meta_call_weight(None).saturating_add(T::WeightInfo::cancel_meta_nonce())
+		)]
+		pub fn meta_cancel_nonce(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+			valid_until: BlockNumberFor<T>,
+			signature: EIP712Signature,
+		) -> DispatchResult {
+			// This is an unsigned transaction
+			ensure_none(origin)?;
+
+			let next_nonce = Self::ensure_can_cancel_meta_nonce(&who, nonce_key, up_to)?;
+			// The typed data signs no fee bound
+			let terms = MetaCallTerms {
+				tip: Zero::zero(),
+				max_fee: u128::MAX.saturated_into(),
+				max_relayer_fee: Zero::zero(),
+				valid_after: Zero::zero(),
+				valid_until,
+				paymaster: None,
+				nonce_key,
+			};
+			let cancel_call =
+				Call::<T>::meta_cancel_nonce { who: who.clone(), nonce_key, up_to, valid_until, signature };
+			let len = cancel_call.encoded_size();
+			let info = cancel_call.get_dispatch_info();
+			let fee_call = <T as Config>::RuntimeCall::from(cancel_call).into();
+			let dispatch = |_origin: T::RuntimeOrigin| {
+				Self::move_lane_nonce_past(who.clone(), nonce_key, up_to);
+				PostDispatchInfo { actual_weight: None, pays_fee: Pays::Yes }
+			};
+			Self::charge_and_dispatch(
 				&who,
+				next_nonce,
+				Some(terms),
+				FeePayer::Signer,
+				None,
+				len,
 				&info,
-				&post_info,
-				actual_fee,
-				tip,
-				already_withdrawn,
+				&fee_call,
+				dispatch,
 			)
-			.map_err(|_err| Error::<T>::PaymentError)?;
-			Self::deposit_event(Event::TransactionFeePaid { who: who.clone(), actual_fee, tip });
+		}
+
+		/// Unregisters the paymaster of the signer
+		///
+		/// The users of the paymaster are removed from `PaymasterSpent`, the rest of the budget
+		/// and the deposits are released.
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::unregister_paymaster(T::MaxPaymasterUsers::get()))]
+		pub fn unregister_paymaster(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+			let sponsor = ensure_signed(origin)?;
+
+			let paymaster = Paymasters::<T>::take(&sponsor).ok_or(Error::<T>::PaymasterNotFound)?;
+			let _ = PaymasterSpent::<T>::clear_prefix(&sponsor, paymaster.users, None);
+			T::Currency::unreserve(&sponsor, paymaster.budget.saturating_add(paymaster.deposit));
+
+			Self::deposit_event(Event::PaymasterUnregistered { sponsor });
+			Ok(Some(T::WeightInfo::unregister_paymaster(paymaster.users)).into())
+		}
+
+		/// Meta-transactions of several users, submitted by a signed relayer in one extrinsic
+		///
+		/// Every item is signed as the `SubstrateCall` v2 typed data, like `meta_call_v2`. A
+		/// failed item is reverted without voiding the others, the result of every item is in a
+		/// `BundleItemDone` event. If `relayer_pays` is set, the relayer pays all fees, otherwise
+		/// every user is charged like `relayed_meta_call` and the relayer only pays for the
+		/// verification.
+		#[pallet::call_index(8)]
+		#[pallet::weight(Pallet::<T>::bundle_weight(items))]
+		pub fn meta_call_bundle(
+			origin: OriginFor<T>,
+			items: Vec<MetaCallItemOf<T>>,
+			relayer_pays: bool,
+		) -> DispatchResultWithPostInfo {
+			let relayer = ensure_signed(origin)?;
+
+			ensure!(!items.is_empty(), Error::<T>::EmptyBundle);
+			ensure!(
+				items.len() <= T::MaxBundleItems::get() as usize,
+				Error::<T>::TooManyBundleItems
+			);
+
+			let mut actual_weight = Weight::zero();
+			for (index, item) in items.into_iter().enumerate() {
+				let MetaCallItem { who, call, nonce, signature, terms } = item;
+				let dispatch_item = || -> Result<Weight, DispatchError> {
+					let description = T::CallDescriber::describe(&call);
+					let struct_hash = Self::eip712_struct_hash_v2(
+						who.clone(),
+						&call.encode(),
+						&description,
+						nonce,
+						&terms,
+					);
+					let nonce_key = terms.nonce_key;
+					Self::ensure_signed_meta_call(&who, &struct_hash, &signature, nonce_key, nonce)?;
+
+					if !relayer_pays {
+						Self::do_meta_call(
+							who.clone(),
+							Box::new(call),
+							nonce,
+							Some(terms),
+							Some(&relayer),
+						)?;
+						return Ok(Weight::zero())
+					}
+					let current_block_number = frame_system::Pallet::<T>::block_number();
+					ensure!(!terms.is_expired_at(current_block_number), Error::<T>::MetaCallExpired);
+					ensure!(
+						!terms.is_premature_at(current_block_number),
+						Error::<T>::MetaCallNotYetValid
+					);
+					ensure!(
+						T::CallFilter::contains(&who, call.into_ref(), nonce_key, nonce),
+						frame_system::Error::<T>::CallFiltered
+					);
+					Self::bump_nonce(&who, nonce_key, nonce)?;
+					let info = call.get_dispatch_info();
+					let call_result =
+						call.dispatch(Self::meta_call_origin(&who, nonce_key, nonce));
+					let call_weight =
+						frame_support::dispatch::extract_actual_weight(&call_result, &info);
+					Self::deposit_event(Event::CallDone { who: who.clone(), call_result });
+					Self::deposit_event(Event::MetaCallSponsored {
+						who: who.clone(),
+						sponsor: relayer.clone(),
+					});
+					Ok(call_weight)
+				};
+				let result = frame_support::storage::with_storage_layer(dispatch_item);
+				actual_weight.saturating_accrue(Self::relayed_meta_call_weight());
+				if let Ok(call_weight) = result {
+					actual_weight.saturating_accrue(call_weight);
+				}
+				Self::deposit_event(Event::BundleItemDone {
+					who,
+					index: index as u32,
+					result: result.map(|_| ()),
+				});
+			}
+
+			Ok(Some(actual_weight).into())
+		}
+
+		/// Meta-transaction from EVM compatible chains, signed as `meta_call_v2` and submitted by a
+		/// signed relayer
+		///
+		/// The user is charged like `meta_call_v2`, and the relayer takes `RelayerServiceFeeShare`
+		/// of the service fee, capped by the signed `max_relayer_fee`.
+		#[pallet::call_index(9)]
+		#[pallet::weight({
+			let di = call.get_dispatch_info();
+			(
+				Pallet::<T>::relayed_meta_call_weight().saturating_add(di.weight),
+				di.class
+			)
+		})]
+		pub fn relayed_meta_call(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			call: Box<<T as Config>::RuntimeCall>,
+			nonce: Nonce,
+			signature: EIP712Signature,
+			terms: MetaCallTermsOf<T>,
+		) -> DispatchResult {
+			let relayer = ensure_signed(origin)?;
+
+			let call_data = <T as Config>::RuntimeCall::encode(&call);
+			let description = T::CallDescriber::describe(&call);
+			let struct_hash =
+				Self::eip712_struct_hash_v2(who.clone(), &call_data, &description, nonce, &terms);
+			Self::ensure_signed_meta_call(&who, &struct_hash, &signature, terms.nonce_key, nonce)?;
+
+			Self::do_meta_call(who, call, nonce, Some(terms), Some(&relayer))
+		}
+
+		/// Updates the EIP-712 domain
+		///
+		/// If `grace_period` is set, signatures made for the replaced domain are still accepted
+		/// for that many blocks, so users with pending signatures are not stranded.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::set_eip712_domain())]
+		pub fn set_eip712_domain(
+			origin: OriginFor<T>,
+			domain: EIP712DomainParams,
+			grace_period: Option<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			T::EIP712DomainOrigin::ensure_origin(origin)?;
+
+			let previous_domain = CurrentEIP712Domain::<T>::get();
+			let previous_valid_until = grace_period.map(|grace_period| {
+				frame_system::Pallet::<T>::block_number().saturating_add(grace_period)
+			});
+			match previous_valid_until {
+				Some(valid_until) => PreviousEIP712Domain::<T>::put((previous_domain, valid_until)),
+				None => PreviousEIP712Domain::<T>::kill(),
+			}
+			CurrentEIP712Domain::<T>::put(&domain);
+
+			Self::deposit_event(Event::EIP712DomainUpdated { domain, previous_valid_until });
+			Ok(())
+		}
+
+		/// Pauses all meta-calls, e.g. when a bug is found in the signature verification or the
+		/// address converter
+		///
+		/// If `unpause_at` is set, the meta-calls are unpaused at that block.
+		#[pallet::call_index(12)]
+		#[pallet::weight(T::WeightInfo::pause())]
+		pub fn pause(
+			origin: OriginFor<T>,
+			unpause_at: Option<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
 
+			if let Some(unpause_at) = unpause_at {
+				ensure!(
+					unpause_at > frame_system::Pallet::<T>::block_number(),
+					Error::<T>::InvalidUnpauseBlock
+				);
+			}
+			Paused::<T>::put(true);
+			UnpauseAt::<T>::set(unpause_at);
+
+			Self::deposit_event(Event::MetaCallsPaused { unpause_at });
+			Ok(())
+		}
+
+		/// Unpauses the meta-calls
+		#[pallet::call_index(13)]
+		#[pallet::weight(T::WeightInfo::unpause())]
+		pub fn unpause(origin: OriginFor<T>) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(Paused::<T>::get(), Error::<T>::NotPaused);
+			Self::do_unpause();
+			Ok(())
+		}
+
+		/// Freezes the meta-calls of `who`, e.g. when its key is compromised
+		#[pallet::call_index(14)]
+		#[pallet::weight(T::WeightInfo::freeze_account())]
+		pub fn freeze_account(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			FrozenAccounts::<T>::insert(&who, ());
+
+			Self::deposit_event(Event::AccountFrozen { who });
+			Ok(())
+		}
+
+		/// Thaws the meta-calls of `who`
+		#[pallet::call_index(15)]
+		#[pallet::weight(T::WeightInfo::thaw_account())]
+		pub fn thaw_account(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(FrozenAccounts::<T>::contains_key(&who), Error::<T>::AccountNotFrozen);
+			FrozenAccounts::<T>::remove(&who);
+
+			Self::deposit_event(Event::AccountThawed { who });
 			Ok(())
 		}
 	}
 
 	impl<T: Config> Pallet<T>
 	where
+		PaymentBalanceOf<T>: FixedPointOperand,
+		BalanceOf<T>: FixedPointOperand,
+		<T as frame_system::Config>::RuntimeCall:
+			Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
 		T: frame_system::Config<AccountId = sp_runtime::AccountId32>,
 	{
-		pub(crate) fn eip712_message_hash(
-			who: T::AccountId,
-			call_data: &[u8],
+		/// The sender of an unsigned meta-call, with the lane and the nonce of the meta-call, a
+		/// `meta_cancel_nonce` takes the next nonce of its lane.
+		fn unsigned_meta_call_sender(
+			unsigned_call: &Call<T>,
+		) -> Option<(&T::AccountId, NonceKey, Nonce)> {
+			match unsigned_call {
+				Call::meta_call { who, nonce, .. } |
+				Call::meta_call_template { who, nonce, .. } |
+				Call::meta_batch_call { who, nonce, .. } => Some((who, 0, *nonce)),
+				Call::meta_call_v2 { who, nonce, terms, .. } =>
+					Some((who, terms.nonce_key, *nonce)),
+				Call::meta_cancel_nonce { who, nonce_key, .. } =>
+					Some((who, *nonce_key, Self::lane_nonce(who, *nonce_key))),
+				_ => None,
+			}
+		}
+
+		/// Checks the sender of an unsigned meta-call hasn't reached `MaxMetaCallsPerPeriod`,
+		/// and the meta-call fits in the share of a block the meta-calls can take.
+		///
+		/// The pending meta-calls of the lane wait for the earlier nonces, so every one of them
+		/// takes a slot too, an account can't flood the pool with future nonces. The lanes are
+		/// capped by `MaxNonceLanes`, so the pending meta-calls of an account are bounded too.
+		fn check_meta_call_limits(unsigned_call: &Call<T>) -> Result<(), TransactionValidityError> {
+			let (max_weight, max_len) = Self::meta_call_block_limits();
+			if unsigned_call.get_dispatch_info().weight.any_gt(max_weight) ||
+				unsigned_call.encoded_size() as u32 > max_len
+			{
+				return Err(InvalidTransaction::ExhaustsResources.into())
+			}
+
+			let max_calls = T::MaxMetaCallsPerPeriod::get();
+			let Some((who, nonce_key, nonce)) = Self::unsigned_meta_call_sender(unsigned_call) else {
+				return Ok(())
+			};
+			if !Self::is_valid_nonce_lane(nonce_key) {
+				return Err(InvalidTransaction::ExhaustsResources.into())
+			}
+			if max_calls == 0 {
+				return Ok(())
+			}
+			let used = MetaCallsInPeriod::<T>::get(Self::meta_call_rate_period_index(), who);
+			let pending = nonce.saturating_sub(Self::lane_nonce(who, nonce_key));
+			if u64::from(used).saturating_add(pending) >= u64::from(max_calls) {
+				return Err(MetaCallValidityError::RateLimited.into())
+			}
+			Ok(())
+		}
+
+		/// Counts an unsigned meta-call included in the block, it is rejected if the meta-calls
+		/// of the block would take more than `MaxMetaCallBlockShare` of it.
+		fn note_meta_call_in_block(
+			unsigned_call: &Call<T>,
+		) -> Result<(), TransactionValidityError> {
+			let (max_weight, max_len) = Self::meta_call_block_limits();
+			let (used_weight, used_len) = MetaCallBlockUsage::<T>::get();
+			let weight = used_weight.saturating_add(unsigned_call.get_dispatch_info().weight);
+			let len = used_len.saturating_add(unsigned_call.encoded_size() as u32);
+			if weight.any_gt(max_weight) || len > max_len {
+				// The block author leaves it in the pool for the next blocks
+				return Err(InvalidTransaction::ExhaustsResources.into())
+			}
+			MetaCallBlockUsage::<T>::put((weight, len));
+
+			if T::MaxMetaCallsPerPeriod::get() > 0 {
+				if let Some((who, ..)) = Self::unsigned_meta_call_sender(unsigned_call) {
+					let period = Self::meta_call_rate_period_index();
+					MetaCallsInPeriod::<T>::mutate(period, who, |used| used.saturating_inc());
+					if !OldestMetaCallPeriod::<T>::exists() {
+						OldestMetaCallPeriod::<T>::put(period);
+					}
+				}
+			}
+			Ok(())
+		}
+
+		/// Validates a meta-call, `terms` is `None` for the legacy typed data.
+		fn validate_meta_call(
+			who: &T::AccountId,
+			call: &<T as Config>::RuntimeCall,
 			nonce: Nonce,
-		) -> Keccak256Signature {
-			use alloc::vec;
+			signature: &EIP712Signature,
+			terms: Option<&MetaCallTermsOf<T>>,
+		) -> TransactionValidity {
+			let nonce_key = terms.map_or(0, |terms| terms.nonce_key);
+			Self::ensure_calls_allowed(who, [call], nonce_key, nonce)?;
 
-			// TODO: will refactor this in Kevin's way for performance.
-			let eip712_domain = crate::eip712::EIP712Domain {
-				name: T::EIP712Name::get(),
-				version: T::EIP712Version::get(),
-				chain_id: T::EIP712ChainID::get(),
-				verifying_contract: T::EIP712VerifyingContractAddress::get(),
-				salt: None,
+			let call_data = <T as Config>::RuntimeCall::encode(call);
+			let description = terms.map(|_| T::CallDescriber::describe(call));
+			let struct_hash = match (terms, &description) {
+				(Some(terms), Some(description)) =>
+					Self::eip712_struct_hash_v2(who.clone(), &call_data, description, nonce, terms),
+				_ => Self::eip712_struct_hash(who.clone(), &call_data, nonce),
+			};
+			let sponsor = terms.and_then(|terms| terms.paymaster.as_ref());
+			let fee_payer = match sponsor.zip(description.as_ref()) {
+				Some((sponsor, description)) => FeePayer::Paymaster(sponsor, description),
+				None => Self::signer_fee_payer(who, [call], terms),
 			};
-			let domain_separator = eip712_domain.separator();
+			Self::validate_signed_meta_call(
+				who,
+				call_data.len(),
+				&call.get_dispatch_info(),
+				nonce,
+				signature,
+				&struct_hash,
+				terms,
+				fee_payer,
+			)
+		}
 
-			let type_hash = sp_io::hashing::keccak_256(
-				"SubstrateCall(string who,bytes callData,uint64 nonce)".as_bytes(),
-			);
-			// Token::Uint(U256::from(keccak_256(&self.name)))
-			use sp_core::crypto::Ss58Codec;
-			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
-			let hashed_call_data = sp_io::hashing::keccak_256(call_data);
-			let message_hash = sp_io::hashing::keccak_256(&ethabi::encode(&[
-				ethabi::Token::FixedBytes(type_hash.to_vec()),
-				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
-				ethabi::Token::FixedBytes(hashed_call_data.to_vec()),
-				ethabi::Token::Uint(nonce.into()),
-			]));
+		/// Validates a meta-call whose signed struct hash is `struct_hash`, `len` and `info` are
+		/// of the calls to dispatch.
+		#[allow(clippy::too_many_arguments)]
+		fn validate_signed_meta_call(
+			who: &T::AccountId,
+			len: usize,
+			info: &DispatchInfo,
+			nonce: Nonce,
+			signature: &EIP712Signature,
+			struct_hash: &Keccak256Signature,
+			terms: Option<&MetaCallTermsOf<T>>,
+			fee_payer: FeePayer<'_, T::AccountId>,
+		) -> TransactionValidity {
+			// Check the signature
+			Self::verify_signer(who, signature, struct_hash)?;
+
+			// Skip frame_system::CheckNonZeroSender
+			// frame_system::CheckSpecVersion<Runtime>, frame_system::CheckTxVersion<Runtime>
+			// and frame_system::CheckGenesis<Runtime> are covered by the v2 and the batch
+			// signatures, the legacy typed data skips them
+
+			// frame_system::CheckEra<Runtime>, the legacy typed data is immortal
+			let current_block_number = frame_system::Pallet::<T>::block_number();
+			let mut longevity = DEFAULT_META_CALL_LONGEVITY;
+			if let Some(terms) = terms {
+				if terms.is_expired_at(current_block_number) {
+					return Err(InvalidTransaction::Stale.into())
+				}
+				if terms.is_premature_at(current_block_number) {
+					return Err(InvalidTransaction::Future.into())
+				}
+				if !terms.valid_until.is_zero() {
+					longevity = terms
+						.valid_until
+						.saturating_sub(current_block_number)
+						.saturated_into::<u64>()
+						.max(1);
+				}
+			}
+
+			// frame_system::CheckNonce<Runtime>, every lane has its own tags
+			let nonce_key = terms.map_or(0, |terms| terms.nonce_key);
+			let account_nonce = Self::lane_nonce(who, nonce_key);
+			if nonce < account_nonce || account_nonce == Nonce::MAX {
+				return Err(InvalidTransaction::Stale.into())
+			}
+			let provides = Self::nonce_tag(who, nonce_key, nonce);
+			let requires = if account_nonce < nonce && nonce > 0u64 {
+				Some(Self::nonce_tag(who, nonce_key, nonce - 1))
+			} else {
+				None
+			};
+			if nonce != account_nonce {
+				return Err(if nonce < account_nonce {
+					InvalidTransaction::Stale
+				} else {
+					InvalidTransaction::Future
+				}
+				.into())
+			}
+
+			// Skip frame_system::CheckWeight<Runtime>
+			// it has implemented `validate_unsigned` and `pre_dispatch_unsigned`, we don't need to
+			// do the validate here.
+
+			// pallet_transaction_payment::ChargeTransactionPayment<Runtime>
+			let tip = terms.map_or_else(Zero::zero, |terms| terms.tip);
+			// We shall get the same `fee` later
+			let est_fee =
+				pallet_transaction_payment::Pallet::<T>::compute_fee(len as u32, info, tip);
+			let payer = match fee_payer {
+				FeePayer::Signer => Some(who),
+				FeePayer::Paymaster(sponsor, _) => Some(sponsor),
+				// The free quota pays nothing, so the fee checks are skipped
+				FeePayer::FreeQuota => None,
+			};
+			if let Some(payer) = payer {
+				if !Self::is_fee_within_bound(est_fee, terms) {
+					return Err(MetaCallValidityError::MaxFeeExceeded.into())
+				}
+				// TODO: Need check this work with assets-payment
+				// We don't withdraw the fee here, because we can't cache the imbalance
+				// Instead, we check the account has enough fee
+				// I think this is a hack, or the type can't match
+				let est_fee = est_fee.saturated_into::<u128>();
+				// We can't get the actual size of the meta-tx itself,
+				// so we have to introducing service fee.
+				let service_fee = T::ServiceFee::get().saturated_into::<u128>();
+				if let FeePayer::Paymaster(sponsor, description) = fee_payer {
+					// The budget is reserved, so the paymaster can pay what it covers
+					Self::check_paymaster(
+						sponsor,
+						who,
+						description,
+						est_fee.saturating_add(service_fee),
+					)
+					.map_err(Self::paymaster_validity_error)?;
+				} else {
+					let usable_balance_for_fees = T::Currency::reducible_balance(
+						payer,
+						Preservation::Preserve,
+						Fortitude::Polite,
+					)
+					.saturated_into::<u128>();
+					if est_fee.saturating_add(service_fee) > usable_balance_for_fees {
+						return Err(MetaCallValidityError::InsufficientBalanceForFees.into())
+					}
+				}
+			}
+
+			// Calculate priority
+			// Cheat from `get_priority` in frame/transaction-payment/src/lib.rs, the priority grows
+			// with the signed tip. Like a signed extrinsic it is scaled by the weight and the length
+			// of the call, a larger call needs a higher tip for the same priority.
+			use frame_support::traits::Defensive;
+			use sp_runtime::traits::One;
+			// Calculate how many such extrinsics we could fit into an empty block and take the
+			// limiting factor.
+			let max_block_weight = <T as frame_system::Config>::BlockWeights::get().max_block;
+			let max_block_length =
+				*<T as frame_system::Config>::BlockLength::get().max.get(info.class) as u64;
+
+			// bounded_weight is used as a divisor later so we keep it non-zero.
+			let bounded_weight = info.weight.max(Weight::from_parts(1, 1)).min(max_block_weight);
+			let bounded_length = (len as u64).clamp(1, max_block_length);
+
+			// returns the scarce resource, i.e. the one that is limiting the number of
+			// transactions.
+			let max_tx_per_block_weight = max_block_weight
+				.checked_div_per_component(&bounded_weight)
+				.defensive_proof("bounded_weight is non-zero; qed")
+				.unwrap_or(1);
+			let max_tx_per_block_length = max_block_length / bounded_length;
+			// Given our current knowledge this value is going to be in a reasonable range - i.e.
+			// less than 10^9 (2^30), so multiplying by the `tip` value is unlikely to overflow the
+			// balance type. We still use saturating ops obviously, but the point is to end up with
+			// some `priority` distribution instead of having all transactions saturate the
+			// priority.
+			let max_tx_per_block = max_tx_per_block_length
+				.min(max_tx_per_block_weight)
+				.saturated_into::<PaymentBalanceOf<T>>();
+			let max_reward = |val: PaymentBalanceOf<T>| val.saturating_mul(max_tx_per_block);
+
+			// To distribute no-tip transactions a little bit, we increase the tip value by one.
+			// This means that given two transactions without a tip, smaller one will be preferred.
+			let tip = tip.saturating_add(One::one());
+			let scaled_tip = max_reward(tip);
+
+			let priority = scaled_tip.saturated_into::<TransactionPriority>();
+
+			// Finish the validation
+			let valid_transaction_builder = ValidTransaction::with_tag_prefix("EVMAccountMapping")
+				.priority(priority)
+				.and_provides(provides)
+				.longevity(longevity)
+				.propagate(true);
+			let Some(requires) = requires else { return valid_transaction_builder.build() };
+			valid_transaction_builder.and_requires(requires).build()
+		}
+
+		/// Dispatches a meta-call, `terms` is `None` for the legacy typed data, `relayer` is the
+		/// signed submitter if any.
+		fn do_meta_call(
+			who: T::AccountId,
+			call: Box<<T as Config>::RuntimeCall>,
+			nonce: Nonce,
+			terms: Option<MetaCallTermsOf<T>>,
+			relayer: Option<&T::AccountId>,
+		) -> DispatchResult {
+			// The signed submissions skip `validate_unsigned`, don't charge them for a forbidden
+			// call
+			let nonce_key = terms.as_ref().map_or(0, |terms| terms.nonce_key);
+			ensure!(
+				T::CallFilter::contains(&who, (*call).into_ref(), nonce_key, nonce),
+				frame_system::Error::<T>::CallFiltered
+			);
+
+			let len = call.encoded_size();
+			let info = call.get_dispatch_info();
+			let fee_call = (*call).clone().into();
+			let sponsor = terms.as_ref().and_then(|terms| terms.paymaster.clone());
+			let description = sponsor.as_ref().map(|_| T::CallDescriber::describe(&call));
+			let fee_payer = match sponsor.as_ref().zip(description.as_ref()) {
+				Some((sponsor, description)) => FeePayer::Paymaster(sponsor, description),
+				None => Self::signer_fee_payer(&who, [&*call], terms.as_ref()),
+			};
+			let dispatch = |origin: T::RuntimeOrigin| {
+				let call_result = call.dispatch(origin);
+				let post_info = match call_result {
+					Ok(post_info) => post_info,
+					Err(error_and_info) => error_and_info.post_info,
+				};
+				// Deposit the call's result
+				Self::deposit_event(Event::CallDone { who: who.clone(), call_result });
+				post_info
+			};
+			Self::charge_and_dispatch(
+				&who, nonce, terms, fee_payer, relayer, len, &info, &fee_call, dispatch,
+			)
+		}
+
+		/// Checks a meta-call submitted in a signed extrinsic, `signature` must be made by `who`
+		/// over `struct_hash`, and `nonce` must be the next one of the lane `nonce_key`.
+		///
+		/// Signed extrinsics skip `validate_unsigned`, so they check the meta-call here.
+		fn ensure_signed_meta_call(
+			who: &T::AccountId,
+			struct_hash: &Keccak256Signature,
+			signature: &EIP712Signature,
+			nonce_key: NonceKey,
+			nonce: Nonce,
+		) -> DispatchResult {
+			Self::ensure_meta_calls_enabled(who)?;
+			Self::verify_signer(who, signature, struct_hash)
+				.map_err(|_err| Error::<T>::InvalidSignature)?;
+			ensure!(Self::is_valid_nonce_lane(nonce_key), Error::<T>::InvalidNonceLane);
+			ensure!(Self::lane_nonce(who, nonce_key) == nonce, Error::<T>::NonceError);
+			Ok(())
+		}
+
+		/// Charges the fees of a meta-call, bumps the nonce, and dispatches the calls with
+		/// `dispatch`, `len`, `info` and `fee_call` stand for the calls.
+		///
+		/// The fees are charged to the sponsor if `fee_payer` is a paymaster, and nothing is
+		/// charged if it is the free quota. The `relayer` takes its share of the service fee.
+		#[allow(clippy::too_many_arguments)]
+		fn charge_and_dispatch(
+			who: &T::AccountId,
+			nonce: Nonce,
+			terms: Option<MetaCallTermsOf<T>>,
+			fee_payer: FeePayer<'_, T::AccountId>,
+			relayer: Option<&T::AccountId>,
+			len: usize,
+			info: &DispatchInfo,
+			fee_call: &<T as frame_system::Config>::RuntimeCall,
+			dispatch: impl FnOnce(T::RuntimeOrigin) -> PostDispatchInfo,
+		) -> DispatchResult {
+			// We don't need to re-validate the signature here, `pre_dispatch` verified it again
+			// when the unsigned meta-call was included, and the signed submissions verify it
+			// themselves.
+
+			// The signed submissions skip `validate_unsigned`
+			Self::ensure_meta_calls_enabled(who)?;
+
+			if let Some(terms) = &terms {
+				let current_block_number = frame_system::Pallet::<T>::block_number();
+				ensure!(!terms.is_expired_at(current_block_number), Error::<T>::MetaCallExpired);
+				ensure!(
+					!terms.is_premature_at(current_block_number),
+					Error::<T>::MetaCallNotYetValid
+				);
+			}
+
+			let nonce_key = terms.as_ref().map_or(0, |terms| terms.nonce_key);
+			let payer = match fee_payer {
+				FeePayer::Signer => who,
+				FeePayer::Paymaster(sponsor, _) => sponsor,
+				FeePayer::FreeQuota => {
+					Self::bump_nonce(who, nonce_key, nonce)?;
+					let remaining = Self::use_free_call(who);
+					Self::deposit_event(Event::FreeCallUsed { who: who.clone(), remaining });
+					dispatch(Self::meta_call_origin(who, nonce_key, nonce));
+					return Ok(())
+				},
+			};
+
+			let tip = terms.as_ref().map_or_else(Zero::zero, |terms| terms.tip);
+			let est_fee =
+				pallet_transaction_payment::Pallet::<T>::compute_fee(len as u32, info, tip);
+			// The fee may have changed since `validate_unsigned`
+			ensure!(Self::is_fee_within_bound(est_fee, terms.as_ref()), Error::<T>::MaxFeeExceeded);
+
+			let mut unreserved_budget = BalanceOf::<T>::zero();
+			if let FeePayer::Paymaster(sponsor, description) = fee_payer {
+				let total_fee = est_fee
+					.saturated_into::<u128>()
+					.saturating_add(T::ServiceFee::get().saturated_into::<u128>());
+				Self::check_paymaster(sponsor, who, description, total_fee)?;
+				// The fees are paid from the reserved budget
+				unreserved_budget = total_fee.saturated_into();
+				T::Currency::unreserve(sponsor, unreserved_budget);
+			}
+
+			// `pre_dispatch` checked the balance of the payer again when the unsigned meta-call was
+			// included, a signed submission fails here if the payer can't pay the service fee.
+			use frame_support::traits::tokens::{WithdrawReasons, ExistenceRequirement};
+			let withdrawn = T::Currency::withdraw(
+				payer,
+				T::ServiceFee::get(),
+				WithdrawReasons::FEE,
+				ExistenceRequirement::KeepAlive
+			).map_err(|_err| Error::<T>::PaymentError)?;
+			let withdrawn_fee = withdrawn.peek();
+			let withdrawn = match relayer {
+				Some(relayer) => {
+					let (relayer_fee, withdrawn) =
+						withdrawn.split(Self::relayer_fee(withdrawn_fee, terms.as_ref()));
+					let amount = relayer_fee.peek();
+					T::Currency::resolve_creating(relayer, relayer_fee);
+					Self::deposit_event(Event::RelayerFeePaid {
+						relayer: relayer.clone(),
+						who: who.clone(),
+						amount,
+					});
+					withdrawn
+				},
+				None => withdrawn,
+			};
+			T::OnUnbalancedForServiceFee::on_unbalanced(withdrawn);
+			Self::deposit_event(Event::ServiceFeePaid {
+				who: payer.clone(),
+				actual_fee: withdrawn_fee,
+				expected_fee: T::ServiceFee::get(),
+			});
+
+			Self::bump_nonce(who, nonce_key, nonce)?;
+
+			// Add the service fee
+			let already_withdrawn =
+				<PaymentOnChargeTransaction<T> as OnChargeTransaction<T>>::withdraw_fee(
+					payer,
+					fee_call,
+					info,
+					est_fee,
+					tip,
+				)
+				.map_err(|_err| Error::<T>::PaymentError)?;
+
+			let post_info = dispatch(Self::meta_call_origin(who, nonce_key, nonce));
+
+			let actual_fee = pallet_transaction_payment::Pallet::<T>::compute_actual_fee(
+				len as u32, info, &post_info, tip,
+			);
+			// frame/transaction-payment/src/payment.rs
+			<PaymentOnChargeTransaction<T> as OnChargeTransaction<T>>::correct_and_deposit_fee(
+				payer,
+				info,
+				&post_info,
+				actual_fee,
+				tip,
+				already_withdrawn,
+			)
+			.map_err(|_err| Error::<T>::PaymentError)?;
+			Self::deposit_event(Event::TransactionFeePaid { who: payer.clone(), actual_fee, tip });
+
+			if let FeePayer::Paymaster(sponsor, _) = fee_payer {
+				let charged = actual_fee
+					.saturated_into::<u128>()
+					.saturating_add(withdrawn_fee.saturated_into::<u128>())
+					.saturated_into::<BalanceOf<T>>();
+				// The fees which weren't charged are reserved back into the budget
+				let mut to_reserve = unreserved_budget.saturating_sub(charged);
+				Paymasters::<T>::mutate(sponsor, |paymaster| {
+					let Some(paymaster) = paymaster else { return };
+					paymaster.budget = paymaster.budget.saturating_sub(charged);
+					// Only the quota needs the fees paid for every user
+					if paymaster.user_quota.is_none() {
+						return
+					}
+					if !PaymasterSpent::<T>::contains_key(sponsor, who) {
+						paymaster.users.saturating_inc();
+						paymaster.deposit.saturating_accrue(T::PaymasterUserDeposit::get());
+						to_reserve.saturating_accrue(T::PaymasterUserDeposit::get());
+					}
+					PaymasterSpent::<T>::mutate(sponsor, who, |spent| {
+						*spent = spent.saturating_add(charged)
+					});
+				});
+				T::Currency::reserve(sponsor, to_reserve).map_err(|_err| Error::<T>::PaymentError)?;
+				Self::deposit_event(Event::MetaCallSponsored {
+					who: who.clone(),
+					sponsor: sponsor.clone(),
+				});
+			}
+
+			Ok(())
+		}
+
+		/// The share of `service_fee` the relayer takes, capped by the signed `max_relayer_fee`,
+		/// nothing for the legacy typed data which doesn't sign a cap.
+		fn relayer_fee(
+			service_fee: BalanceOf<T>,
+			terms: Option<&MetaCallTermsOf<T>>,
+		) -> BalanceOf<T> {
+			let Some(terms) = terms else { return Zero::zero() };
+			let relayer_fee = T::RelayerServiceFeeShare::get().mul_floor(service_fee);
+			relayer_fee.min(terms.max_relayer_fee.saturated_into::<u128>().saturated_into())
+		}
+
+		/// Bumps the nonce of the lane `nonce_key` of `who`, which must be `nonce`.
+		///
+		/// A closed lane, whose nonce is `Nonce::MAX`, can't be bumped.
+		fn bump_nonce(
+			who: &T::AccountId,
+			nonce_key: NonceKey,
+			nonce: Nonce,
+		) -> Result<(), Error<T>> {
+			ensure!(Self::is_valid_nonce_lane(nonce_key), Error::<T>::InvalidNonceLane);
+			let bump = |value: &mut Nonce| {
+				if *value != nonce || nonce == Nonce::MAX {
+					return Err(Error::<T>::NonceError)
+				}
+				*value += 1;
+				Ok(())
+			};
+			if nonce_key == 0 {
+				AccountNonce::<T>::try_mutate(who, bump)
+			} else {
+				LaneNonce::<T>::try_mutate(who, nonce_key, bump)
+			}
+		}
+
+		/// Moves the nonce of the lane `nonce_key` of `who` past `up_to`, so the meta-calls signed
+		/// with these nonces are invalidated.
+		fn do_cancel_meta_nonce(
+			who: T::AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+		) -> DispatchResult {
+			Self::ensure_can_cancel_meta_nonce(&who, nonce_key, up_to)?;
+			Self::move_lane_nonce_past(who, nonce_key, up_to);
+			Ok(())
+		}
+
+		/// Checks `who` can move the nonce of the lane `nonce_key` past `up_to`, returns the next
+		/// nonce of the lane.
+		///
+		/// Only the `MaxNonceLanes` lanes can be moved, so the cancellations can't grow
+		/// `LaneNonce` without bound.
+		fn ensure_can_cancel_meta_nonce(
+			who: &T::AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+		) -> Result<Nonce, DispatchError> {
+			ensure!(Self::is_valid_nonce_lane(nonce_key), Error::<T>::InvalidNonceLane);
+			let next_nonce = Self::lane_nonce(who, nonce_key);
+			ensure!(up_to >= next_nonce && next_nonce != Nonce::MAX, Error::<T>::NonceError);
+			ensure!(
+				T::CallFilter::can_cancel(who, nonce_key, next_nonce, up_to),
+				frame_system::Error::<T>::CallFiltered
+			);
+			Ok(next_nonce)
+		}
+
+		/// Sets the nonce of the lane `nonce_key` of `who` to `up_to + 1`.
+		fn move_lane_nonce_past(who: T::AccountId, nonce_key: NonceKey, up_to: Nonce) {
+			let next_nonce = up_to.saturating_add(1);
+			if nonce_key == 0 {
+				AccountNonce::<T>::insert(&who, next_nonce);
+			} else {
+				LaneNonce::<T>::insert(&who, nonce_key, next_nonce);
+			}
+
+			Self::deposit_event(Event::MetaNonceCancelled { who, nonce_key, next_nonce });
+		}
+
+		/// Whether `nonce_key` is one of the `MaxNonceLanes` lanes of an account.
+		fn is_valid_nonce_lane(nonce_key: NonceKey) -> bool {
+			nonce_key < NonceKey::from(T::MaxNonceLanes::get())
+		}
+
+		/// The `provides` tag of `nonce` in the lane `nonce_key`, lane zero keeps the tags of
+		/// the sequential nonce.
+		fn nonce_tag(who: &T::AccountId, nonce_key: NonceKey, nonce: Nonce) -> Vec<u8> {
+			if nonce_key == 0 {
+				(who, nonce).encode()
+			} else {
+				(who, nonce_key, nonce).encode()
+			}
+		}
+
+		/// The origin the meta-call `nonce` of the lane `nonce_key` of `who` is dispatched with,
+		/// filtered by `CallFilter`.
+		fn meta_call_origin(
+			who: &T::AccountId,
+			nonce_key: NonceKey,
+			nonce: Nonce,
+		) -> T::RuntimeOrigin {
+			let mut origin: T::RuntimeOrigin = RawOrigin::Signed(who.clone()).into();
+			let sender = who.clone();
+			origin.add_filter(move |call| T::CallFilter::contains(&sender, call, nonce_key, nonce));
+			origin
+		}
+
+		/// Checks the `CallFilter` allows all `calls` of the meta-call `nonce` of the lane
+		/// `nonce_key` of `who`.
+		fn ensure_calls_allowed<'c>(
+			who: &T::AccountId,
+			calls: impl IntoIterator<Item = &'c <T as Config>::RuntimeCall>,
+			nonce_key: NonceKey,
+			nonce: Nonce,
+		) -> Result<(), TransactionValidityError> {
+			let is_allowed = |call: &<T as Config>::RuntimeCall| {
+				T::CallFilter::contains(who, call.into_ref(), nonce_key, nonce)
+			};
+			if calls.into_iter().all(is_allowed) {
+				Ok(())
+			} else {
+				Err(MetaCallValidityError::CallFiltered.into())
+			}
+		}
+
+		/// The signer pays the fees of `calls` unless they are covered by its free quota, which
+		/// requires a zero tip.
+		fn signer_fee_payer<'a, 'c>(
+			who: &T::AccountId,
+			calls: impl IntoIterator<Item = &'c <T as Config>::RuntimeCall>,
+			terms: Option<&MetaCallTermsOf<T>>,
+		) -> FeePayer<'a, T::AccountId> {
+			let is_free = terms.map_or(true, |terms| terms.tip.is_zero()) &&
+				Self::free_calls_left(who) > 0 &&
+				calls.into_iter().all(|call| T::FreeCallFilter::contains(call.into_ref()));
+			if is_free {
+				FeePayer::FreeQuota
+			} else {
+				FeePayer::Signer
+			}
+		}
+
+		/// Counts a free meta-call of `who`, returns the free calls left in the period.
+		fn use_free_call(who: &T::AccountId) -> u32 {
+			let period = Self::free_call_period_index();
+			FreeCallsInPeriod::<T>::mutate(|(total_period, total)| {
+				if *total_period != period {
+					*total_period = period;
+					*total = 0;
+				}
+				total.saturating_inc();
+			});
+			let used = FreeCallsUsed::<T>::mutate(period, who, |used| {
+				used.saturating_inc();
+				*used
+			});
+			if !OldestFreeCallPeriod::<T>::exists() {
+				OldestFreeCallPeriod::<T>::put(period);
+			}
+			T::FreeCallsPerPeriod::get().saturating_sub(used)
+		}
+
+		/// Checks the paymaster of `sponsor` can pay `total_fee` for the described call of `who`.
+		fn check_paymaster(
+			sponsor: &T::AccountId,
+			who: &T::AccountId,
+			description: &CallDescription,
+			total_fee: u128,
+		) -> Result<(), Error<T>> {
+			let paymaster = Paymasters::<T>::get(sponsor).ok_or(Error::<T>::PaymasterNotFound)?;
+			if let Some(expires_at) = paymaster.expires_at {
+				ensure!(
+					frame_system::Pallet::<T>::block_number() <= expires_at,
+					Error::<T>::PaymasterExpired
+				);
+			}
+			ensure!(
+				paymaster.allowed_calls.is_empty() ||
+					paymaster.allowed_calls.iter().any(|policy| policy.matches(description)),
+				Error::<T>::CallNotSponsored
+			);
+			ensure!(
+				total_fee <= paymaster.budget.saturated_into::<u128>(),
+				Error::<T>::PaymasterBudgetExhausted
+			);
+			if let Some(user_quota) = paymaster.user_quota {
+				let spent = PaymasterSpent::<T>::get(sponsor, who).saturated_into::<u128>();
+				ensure!(
+					spent.saturating_add(total_fee) <= user_quota.saturated_into::<u128>(),
+					Error::<T>::PaymasterQuotaExceeded
+				);
+				// A new user is tracked in `PaymasterSpent` for a deposit
+				if !PaymasterSpent::<T>::contains_key(sponsor, who) {
+					ensure!(
+						paymaster.users < T::MaxPaymasterUsers::get(),
+						Error::<T>::PaymasterQuotaExceeded
+					);
+					ensure!(
+						T::Currency::can_reserve(sponsor, T::PaymasterUserDeposit::get()),
+						Error::<T>::PaymasterBudgetExhausted
+					);
+				}
+			}
+			Ok(())
+		}
+
+		/// The validity error of a meta-call rejected by the paymaster.
+		fn paymaster_validity_error(error: Error<T>) -> MetaCallValidityError {
+			match error {
+				Error::<T>::PaymasterNotFound => MetaCallValidityError::PaymasterNotFound,
+				Error::<T>::PaymasterExpired => MetaCallValidityError::PaymasterExpired,
+				Error::<T>::CallNotSponsored => MetaCallValidityError::CallNotSponsored,
+				Error::<T>::PaymasterQuotaExceeded => MetaCallValidityError::PaymasterQuotaExceeded,
+				_ => MetaCallValidityError::PaymasterBudgetExhausted,
+			}
+		}
+
+		/// Checks the estimated transaction fee plus the service fee doesn't exceed the signed
+		/// `max_fee`, the legacy typed data has no bound.
+		fn is_fee_within_bound(
+			est_fee: PaymentBalanceOf<T>,
+			terms: Option<&MetaCallTermsOf<T>>,
+		) -> bool {
+			let Some(terms) = terms else { return true };
+			let total_fee = est_fee
+				.saturated_into::<u128>()
+				.saturating_add(T::ServiceFee::get().saturated_into::<u128>());
+			total_fee <= terms.max_fee.saturated_into::<u128>()
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The terms of a `meta_batch_call`, the `SubstrateBatch` typed data only signs the maximum
+		/// total fee and the upper bound of the validity window.
+		fn batch_terms(
+			max_fee: PaymentBalanceOf<T>,
+			valid_until: BlockNumberFor<T>,
+		) -> MetaCallTermsOf<T> {
+			MetaCallTerms {
+				tip: Zero::zero(),
+				max_fee,
+				max_relayer_fee: Zero::zero(),
+				valid_after: Zero::zero(),
+				valid_until,
+				paymaster: None,
+				nonce_key: 0,
+			}
+		}
+
+		/// The dispatch info of a batch, the weights of the calls add up.
+		pub(crate) fn batch_dispatch_info(calls: &[<T as Config>::RuntimeCall]) -> DispatchInfo {
+			let mut info = DispatchInfo::default();
+			for call in calls {
+				info.weight.saturating_accrue(call.get_dispatch_info().weight);
+			}
+			info
+		}
+
+		/// Checks the meta-calls aren't paused and `who` isn't frozen.
+		pub fn ensure_meta_calls_enabled(who: &T::AccountId) -> Result<(), Error<T>> {
+			ensure!(!Paused::<T>::get(), Error::<T>::MetaCallsPaused);
+			ensure!(!FrozenAccounts::<T>::contains_key(who), Error::<T>::AccountFrozen);
+			Ok(())
+		}
+
+		fn do_unpause() {
+			Paused::<T>::kill();
+			UnpauseAt::<T>::kill();
+			Self::deposit_event(Event::MetaCallsUnpaused);
+		}
+
+		/// The weight and the length the unsigned meta-calls can take in a block.
+		pub(crate) fn meta_call_block_limits() -> (Weight, u32) {
+			let share = T::MaxMetaCallBlockShare::get();
+			let max_block = <T as frame_system::Config>::BlockWeights::get().max_block;
+			let max_len =
+				*<T as frame_system::Config>::BlockLength::get().max.get(DispatchClass::Normal);
+			let max_weight =
+				Weight::from_parts(share * max_block.ref_time(), share * max_block.proof_size());
+			(max_weight, share * max_len)
+		}
+
+		/// The index of the current period of the meta-call rate limit.
+		fn meta_call_rate_period_index() -> BlockNumberFor<T> {
+			let period = T::MetaCallRatePeriod::get().max(sp_runtime::traits::One::one());
+			frame_system::Pallet::<T>::block_number() / period
+		}
+
+		/// Removes the entries of the past rate limit periods, at most
+		/// `MAX_META_CALL_RATE_CLEANUP` in a block.
+		fn clean_up_meta_call_periods() -> Weight {
+			let Some(oldest_period) = OldestMetaCallPeriod::<T>::get() else {
+				return T::DbWeight::get().reads(1)
+			};
+			if oldest_period >= Self::meta_call_rate_period_index() {
+				return T::DbWeight::get().reads(1)
+			}
+			let result = MetaCallsInPeriod::<T>::clear_prefix(
+				oldest_period,
+				MAX_META_CALL_RATE_CLEANUP,
+				None,
+			);
+			if result.maybe_cursor.is_none() {
+				let next_period = oldest_period.saturating_add(sp_runtime::traits::One::one());
+				OldestMetaCallPeriod::<T>::put(next_period);
+			}
+			T::DbWeight::get()
+				.reads_writes(1 + u64::from(result.loops), 1 + u64::from(result.unique))
+		}
+
+		/// The index of the current period of the free quota.
+		fn free_call_period_index() -> BlockNumberFor<T> {
+			let period = T::FreeCallPeriod::get().max(sp_runtime::traits::One::one());
+			frame_system::Pallet::<T>::block_number() / period
+		}
+
+		/// Removes the entries of the past free quota periods, at most
+		/// `MAX_META_CALL_RATE_CLEANUP` in a block.
+		pub(crate) fn clean_up_free_call_periods() -> Weight {
+			let Some(oldest_period) = OldestFreeCallPeriod::<T>::get() else {
+				return T::DbWeight::get().reads(1)
+			};
+			if oldest_period >= Self::free_call_period_index() {
+				return T::DbWeight::get().reads(1)
+			}
+			let result =
+				FreeCallsUsed::<T>::clear_prefix(oldest_period, MAX_META_CALL_RATE_CLEANUP, None);
+			if result.maybe_cursor.is_none() {
+				let next_period = oldest_period.saturating_add(sp_runtime::traits::One::one());
+				OldestFreeCallPeriod::<T>::put(next_period);
+			}
+			T::WeightInfo::clean_up_free_call_periods(result.unique)
+		}
+
+		/// The weight of a meta-call signed with `terms` without its calls.
+		///
+		/// The `meta_call` benchmark runs `pre_dispatch` and the dispatch of a meta-call signed in
+		/// the current EIP-712 domain, the signature may be recovered in every other accepted
+		/// domain too. A paymaster also updates its budget, `PaymasterSpent` and the reserve of the
+		/// sponsor, see the `meta_call_paymaster` benchmark. Without a paymaster, the meta-call may
+		/// be covered by the free quota instead, which counts it in `FreeCallsUsed` and
+		/// `FreeCallsInPeriod`, see the `meta_call_free_quota` benchmark.
+		pub(crate) fn meta_call_weight(terms: Option<&MetaCallTermsOf<T>>) -> Weight {
+			let charge_weight = match terms.and_then(|terms| terms.paymaster.as_ref()) {
+				Some(_) => T::WeightInfo::meta_call_paymaster(),
+				None => T::WeightInfo::meta_call().max(T::WeightInfo::meta_call_free_quota()),
+			};
+			charge_weight.saturating_add(Self::other_domains_weight())
+		}
+
+		/// The weight of recovering a signature in every accepted EIP-712 domain but the current
+		/// one.
+		///
+		/// At most the current and the previous domain are accepted, each with the
+		/// `EIP712ExtraChainIDs`. A retired domain is only tried in place of the previous one.
+		fn other_domains_weight() -> Weight {
+			let max_domains = 2 * (1 + T::EIP712ExtraChainIDs::get().len() as u64);
+			T::WeightInfo::verify_signer().saturating_mul(max_domains.saturating_sub(1))
+		}
+
+		/// The weight of a meta-call submitted by a signed relayer without its calls.
+		///
+		/// The `relayed_meta_call` benchmark verifies the signature in the extrinsic, has a
+		/// paymaster pay the fees and credits the relayer with its share of the service fee.
+		pub(crate) fn relayed_meta_call_weight() -> Weight {
+			T::WeightInfo::relayed_meta_call().saturating_add(Self::other_domains_weight())
+		}
+
+		/// The weight of a bundle, every item adds the weight of a `relayed_meta_call`.
+		pub(crate) fn bundle_weight(items: &[MetaCallItemOf<T>]) -> Weight {
+			items.iter().fold(Weight::zero(), |weight, item| {
+				weight
+					.saturating_add(Self::relayed_meta_call_weight())
+					.saturating_add(item.call.get_dispatch_info().weight)
+			})
+		}
+	}
+
+	impl<T: Config> Pallet<T>
+	where
+		T: frame_system::Config<AccountId = sp_runtime::AccountId32>,
+	{
+		/// The EIP-712 domains a signature may be made for, the current one goes first.
+		pub fn eip712_domains() -> Vec<EIP712DomainParams> {
+			let mut domains = alloc::vec![CurrentEIP712Domain::<T>::get()];
+			if let Some((previous_domain, valid_until)) = PreviousEIP712Domain::<T>::get() {
+				if frame_system::Pallet::<T>::block_number() <= valid_until {
+					domains.push(previous_domain);
+				}
+			}
+
+			let extra_chain_ids = T::EIP712ExtraChainIDs::get();
+			for domain in domains.clone() {
+				for chain_id in extra_chain_ids.iter() {
+					if *chain_id != domain.chain_id {
+						domains.push(EIP712DomainParams { chain_id: *chain_id, ..domain.clone() });
+					}
+				}
+			}
+			domains
+		}
+
+		/// Checks `who` signed the struct hash in any of the accepted EIP-712 domains.
+		pub(crate) fn verify_signer(
+			who: &T::AccountId,
+			signature: &EIP712Signature,
+			struct_hash: &Keccak256Signature,
+		) -> Result<(), TransactionValidityError> {
+			let mut first_error = None;
+			for domain in Self::eip712_domains() {
+				let message_hash = Self::eip712_typed_data_hash(&domain, struct_hash);
+				let result = Self::recover_signer(signature, &message_hash).and_then(|signer| {
+					if &signer == who {
+						Ok(())
+					} else {
+						Err(InvalidTransaction::BadSigner.into())
+					}
+				});
+				match result {
+					Ok(()) => return Ok(()),
+					Err(err) => {
+						first_error.get_or_insert(err);
+					},
+				}
+			}
+			// Tell a signature made before the domain was replaced from a bad one
+			let bad_signer: TransactionValidityError = InvalidTransaction::BadSigner.into();
+			if first_error == Some(bad_signer) &&
+				Self::is_signed_in_retired_domain(who, signature, struct_hash)
+			{
+				return Err(MetaCallValidityError::DomainMismatch.into())
+			}
+			Err(first_error.unwrap_or_else(|| InvalidTransaction::BadSigner.into()))
+		}
+
+		/// Checks `who` signed the struct hash in the replaced EIP-712 domain whose grace period
+		/// has passed.
+		fn is_signed_in_retired_domain(
+			who: &T::AccountId,
+			signature: &EIP712Signature,
+			struct_hash: &Keccak256Signature,
+		) -> bool {
+			let Some((previous_domain, valid_until)) = PreviousEIP712Domain::<T>::get() else {
+				return false
+			};
+			if frame_system::Pallet::<T>::block_number() <= valid_until {
+				return false
+			}
+			let message_hash = Self::eip712_typed_data_hash(&previous_domain, struct_hash);
+			Self::recover_signer(signature, &message_hash).map_or(false, |signer| &signer == who)
+		}
+
+		/// Recovers the account which signed the message hash.
+		pub(crate) fn recover_signer(
+			signature: &EIP712Signature,
+			message_hash: &Keccak256Signature,
+		) -> Result<T::AccountId, TransactionValidityError> {
+			let recovered_public_key = match (match <T as Config>::AddressConverter::SECP256K1_PUBLIC_KEY_FORM {
+				Secp256K1PublicKeyForm::Compressed => {
+					sp_io::crypto::secp256k1_ecdsa_recover_compressed(signature, message_hash)
+						.map(|i| i.to_vec())
+				},
+				Secp256K1PublicKeyForm::Uncompressed => {
+					sp_io::crypto::secp256k1_ecdsa_recover(signature, message_hash)
+						.map(|i| i.to_vec())
+				}
+			}) {
+				Ok(recovered_public_key) => recovered_public_key,
+				Err(sp_io::EcdsaVerifyError::BadSignature) =>
+					return Err(MetaCallValidityError::RecoveryFailed.into()),
+				Err(_bad_rs_or_v) =>
+					return Err(MetaCallValidityError::BadSignatureEncoding.into()),
+			};
+
+			// Deserialize the actual caller
+			let Some(decoded_account) =
+				<T as Config>::AddressConverter::try_convert(&recovered_public_key) else {
+				return Err(MetaCallValidityError::UnsupportedPublicKeyForm.into())
+			};
+			Ok(decoded_account)
+		}
+
+		/// The nonce of the next meta-call of `who`.
+		pub fn account_nonce(who: &T::AccountId) -> Nonce {
+			AccountNonce::<T>::get(who)
+		}
+
+		/// The nonce of the next meta-call of `who` in the lane `nonce_key`.
+		pub fn lane_nonce(who: &T::AccountId, nonce_key: NonceKey) -> Nonce {
+			if nonce_key == 0 {
+				AccountNonce::<T>::get(who)
+			} else {
+				LaneNonce::<T>::get(who, nonce_key)
+			}
+		}
+
+		/// The free meta-calls `who` has left in the current period, zero if `who` holds at least
+		/// `FreeCallBalanceThreshold`, the quota is disabled, or all accounts have used
+		/// `MaxFreeCallsPerPeriod` free meta-calls in the period.
+		pub fn free_calls_left(who: &T::AccountId) -> u32 {
+			if T::FreeCallPeriod::get().is_zero() ||
+				<T::Currency as Currency<T::AccountId>>::total_balance(who) >=
+					T::FreeCallBalanceThreshold::get()
+			{
+				return 0
+			}
+			let period = Self::free_call_period_index();
+			let (total_period, total) = FreeCallsInPeriod::<T>::get();
+			if total_period == period && total >= T::MaxFreeCallsPerPeriod::get() {
+				return 0
+			}
+			T::FreeCallsPerPeriod::get().saturating_sub(FreeCallsUsed::<T>::get(period, who))
+		}
+
+		/// The account mapped from the EVM public key.
+		pub fn account_of(evm_public_key: &[u8]) -> Option<T::AccountId> {
+			<T as Config>::AddressConverter::try_convert(evm_public_key)
+		}
+
+		/// The account mapped from the EVM address, if the `AddressConverter` supports it.
+		pub fn account_of_address(evm_address: &sp_core::H160) -> Option<T::AccountId> {
+			<T as Config>::AddressConverter::try_convert_address(evm_address)
+		}
+
+		/// Everything besides the message a client needs to build the typed data.
+		pub fn typed_data_context() -> TypedDataContext {
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			TypedDataContext {
+				domain: CurrentEIP712Domain::<T>::get(),
+				salt: T::EIP712Salt::get(),
+				ss58_prefix: T::SS58Prefix::get(),
+				genesis_hash: frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero())
+					.as_ref()
+					.to_vec(),
+				spec_version: runtime_version.spec_version,
+				transaction_version: runtime_version.transaction_version,
+			}
+		}
+
+		/// The message hash of the legacy typed data in the current EIP-712 domain.
+		pub fn eip712_message_hash(
+			who: T::AccountId,
+			call_data: &[u8],
+			nonce: Nonce,
+		) -> Keccak256Signature {
+			let struct_hash = Self::eip712_struct_hash(who, call_data, nonce);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		/// The description of the encoded call in the v2 typed data, `None` if it can't be decoded.
+		pub fn describe_call(call_data: &[u8]) -> Option<CallDescription> {
+			<T as Config>::RuntimeCall::decode(&mut &call_data[..])
+				.ok()
+				.map(|call| T::CallDescriber::describe(&call))
+		}
+
+		/// The primary type name and the members of the encoded call template, `None` if it can't
+		/// be decoded.
+		pub fn template_members(template: &[u8]) -> Option<TemplateMembers> {
+			let template = TemplateOf::<T>::decode(&mut &template[..]).ok()?;
+			let (primary_type, members) = T::CallTemplates::members(&template);
+			Some(TemplateMembers {
+				primary_type: primary_type.as_bytes().to_vec(),
+				members: members
+					.into_iter()
+					.map(|(name, value)| (name.as_bytes().to_vec(), value))
+					.collect(),
+			})
+		}
+
+		/// The message hash of the v2 typed data in the current EIP-712 domain.
+		///
+		/// The call description is empty if the call data can't be decoded, such a call can't be
+		/// dispatched anyway.
+		pub fn eip712_message_hash_v2(
+			who: T::AccountId,
+			call_data: &[u8],
+			nonce: Nonce,
+			terms: &MetaCallTermsOf<T>,
+		) -> Keccak256Signature {
+			let description = Self::describe_call(call_data).unwrap_or_default();
+			let struct_hash =
+				Self::eip712_struct_hash_v2(who, call_data, &description, nonce, terms);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		pub(crate) fn eip712_struct_hash(
+			who: T::AccountId,
+			call_data: &[u8],
+			nonce: Nonce,
+		) -> Keccak256Signature {
+			let type_hash = sp_io::hashing::keccak_256(SUBSTRATE_CALL_TYPE_V1.as_bytes());
+			// Token::Uint(U256::from(keccak_256(&self.name)))
+			use sp_core::crypto::Ss58Codec;
+			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
+			let hashed_call_data = sp_io::hashing::keccak_256(call_data);
+			sp_io::hashing::keccak_256(&ethabi::encode(&[
+				ethabi::Token::FixedBytes(type_hash.to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
+				ethabi::Token::FixedBytes(hashed_call_data.to_vec()),
+				ethabi::Token::Uint(nonce.into()),
+			]))
+		}
+
+		pub(crate) fn eip712_struct_hash_v2(
+			who: T::AccountId,
+			call_data: &[u8],
+			description: &CallDescription,
+			nonce: Nonce,
+			terms: &MetaCallTermsOf<T>,
+		) -> Keccak256Signature {
+			let type_hash = sp_io::hashing::keccak_256(SUBSTRATE_CALL_TYPE_V2.as_bytes());
+			// Like `CheckGenesis`, `CheckSpecVersion` and `CheckTxVersion`, a signature can't be
+			// replayed on other chains or after a runtime upgrade.
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			use sp_core::crypto::Ss58Codec;
+			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
+			let ss58_paymaster = terms
+				.paymaster
+				.as_ref()
+				.map(|sponsor| sponsor.to_ss58check_with_version(T::SS58Prefix::get().into()))
+				.unwrap_or_default();
+			let hashed_call_data = sp_io::hashing::keccak_256(call_data);
+			sp_io::hashing::keccak_256(&ethabi::encode(&[
+				ethabi::Token::FixedBytes(type_hash.to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.pallet_name).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.call_name).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.call_args).to_vec()),
+				ethabi::Token::FixedBytes(hashed_call_data.to_vec()),
+				ethabi::Token::Uint(nonce.into()),
+				ethabi::Token::Uint(terms.nonce_key.into()),
+				ethabi::Token::Uint(terms.tip.saturated_into::<u128>().into()),
+				ethabi::Token::Uint(terms.max_fee.saturated_into::<u128>().into()),
+				ethabi::Token::Uint(terms.max_relayer_fee.saturated_into::<u128>().into()),
+				ethabi::Token::Uint(terms.valid_after.saturated_into::<u64>().into()),
+				ethabi::Token::Uint(terms.valid_until.saturated_into::<u64>().into()),
+				ethabi::Token::FixedBytes(genesis_hash.as_ref().to_vec()),
+				ethabi::Token::Uint(runtime_version.spec_version.into()),
+				ethabi::Token::Uint(runtime_version.transaction_version.into()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_paymaster.as_bytes()).to_vec()),
+			]))
+		}
+
+		/// The message hash of the `SubstrateBatch` typed data in the current EIP-712 domain,
+		/// `calls` are the encoded calls.
+		pub fn eip712_batch_message_hash(
+			who: T::AccountId,
+			calls: &[Vec<u8>],
+			nonce: Nonce,
+			atomic: bool,
+			max_fee: PaymentBalanceOf<T>,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let struct_hash =
+				Self::eip712_batch_struct_hash(who, calls, nonce, atomic, max_fee, valid_until);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		pub(crate) fn eip712_batch_struct_hash(
+			who: T::AccountId,
+			calls: &[Vec<u8>],
+			nonce: Nonce,
+			atomic: bool,
+			max_fee: PaymentBalanceOf<T>,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let type_hash = sp_io::hashing::keccak_256(SUBSTRATE_BATCH_TYPE.as_bytes());
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			use sp_core::crypto::Ss58Codec;
+			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
+			// An array is hashed as the concatenation of the hashes of its items
+			let hashed_calls = sp_io::hashing::keccak_256(
+				&calls.iter().flat_map(|call| sp_io::hashing::keccak_256(call)).collect::<Vec<_>>(),
+			);
+			sp_io::hashing::keccak_256(&ethabi::encode(&[
+				ethabi::Token::FixedBytes(type_hash.to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
+				ethabi::Token::FixedBytes(hashed_calls.to_vec()),
+				ethabi::Token::Uint(nonce.into()),
+				ethabi::Token::Bool(atomic),
+				ethabi::Token::Uint(max_fee.saturated_into::<u128>().into()),
+				ethabi::Token::Uint(valid_until.saturated_into::<u64>().into()),
+				ethabi::Token::FixedBytes(genesis_hash.as_ref().to_vec()),
+				ethabi::Token::Uint(runtime_version.spec_version.into()),
+				ethabi::Token::Uint(runtime_version.transaction_version.into()),
+			]))
+		}
+
+		/// The message hash of the `SponsoredSubstrateCall` typed data in the current EIP-712
+		/// domain, `sponsor` is `None` if any sponsor may submit it.
+		///
+		/// The call description is empty if the call data can't be decoded.
+		pub fn eip712_sponsored_message_hash(
+			who: T::AccountId,
+			call_data: &[u8],
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+			sponsor: Option<&T::AccountId>,
+		) -> Keccak256Signature {
+			let description = Self::describe_call(call_data).unwrap_or_default();
+			let struct_hash = Self::eip712_sponsored_struct_hash(
+				who,
+				call_data,
+				&description,
+				nonce,
+				valid_until,
+				sponsor,
+			);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		pub(crate) fn eip712_sponsored_struct_hash(
+			who: T::AccountId,
+			call_data: &[u8],
+			description: &CallDescription,
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+			sponsor: Option<&T::AccountId>,
+		) -> Keccak256Signature {
+			let type_hash = sp_io::hashing::keccak_256(SPONSORED_SUBSTRATE_CALL_TYPE.as_bytes());
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			use sp_core::crypto::Ss58Codec;
+			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
+			let ss58_sponsor = sponsor
+				.map(|sponsor| sponsor.to_ss58check_with_version(T::SS58Prefix::get().into()))
+				.unwrap_or_default();
+			let hashed_call_data = sp_io::hashing::keccak_256(call_data);
+			sp_io::hashing::keccak_256(&ethabi::encode(&[
+				ethabi::Token::FixedBytes(type_hash.to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.pallet_name).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.call_name).to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(&description.call_args).to_vec()),
+				ethabi::Token::FixedBytes(hashed_call_data.to_vec()),
+				ethabi::Token::Uint(nonce.into()),
+				ethabi::Token::Uint(valid_until.saturated_into::<u64>().into()),
+				ethabi::Token::FixedBytes(genesis_hash.as_ref().to_vec()),
+				ethabi::Token::Uint(runtime_version.spec_version.into()),
+				ethabi::Token::Uint(runtime_version.transaction_version.into()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_sponsor.as_bytes()).to_vec()),
+			]))
+		}
+
+		/// The message hash of the `CancelMetaNonce` typed data in the current EIP-712 domain.
+		pub fn eip712_cancel_message_hash(
+			who: T::AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let struct_hash = Self::eip712_cancel_struct_hash(who, nonce_key, up_to, valid_until);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		pub(crate) fn eip712_cancel_struct_hash(
+			who: T::AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let type_hash = sp_io::hashing::keccak_256(CANCEL_META_NONCE_TYPE.as_bytes());
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			use sp_core::crypto::Ss58Codec;
+			let ss58_who = who.to_ss58check_with_version(T::SS58Prefix::get().into());
+			sp_io::hashing::keccak_256(&ethabi::encode(&[
+				ethabi::Token::FixedBytes(type_hash.to_vec()),
+				ethabi::Token::FixedBytes(sp_io::hashing::keccak_256(ss58_who.as_bytes()).to_vec()),
+				ethabi::Token::Uint(nonce_key.into()),
+				ethabi::Token::Uint(up_to.into()),
+				ethabi::Token::Uint(valid_until.saturated_into::<u64>().into()),
+				ethabi::Token::FixedBytes(genesis_hash.as_ref().to_vec()),
+				ethabi::Token::Uint(runtime_version.spec_version.into()),
+				ethabi::Token::Uint(runtime_version.transaction_version.into()),
+			]))
+		}
+
+		/// The message hash of a call template in the current EIP-712 domain.
+		pub fn eip712_template_message_hash(
+			template: &TemplateOf<T>,
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let struct_hash = Self::eip712_template_struct_hash(template, nonce, valid_until);
+			Self::eip712_typed_data_hash(&CurrentEIP712Domain::<T>::get(), &struct_hash)
+		}
+
+		pub(crate) fn eip712_template_struct_hash(
+			template: &TemplateOf<T>,
+			nonce: Nonce,
+			valid_until: BlockNumberFor<T>,
+		) -> Keccak256Signature {
+			let (primary_type, members) = T::CallTemplates::members(template);
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			let runtime_version = <T as frame_system::Config>::Version::get();
+			let encoded_type = template_encoded_type(primary_type, &members);
+			let mut tokens = alloc::vec![ethabi::Token::FixedBytes(
+				sp_io::hashing::keccak_256(encoded_type.as_bytes()).to_vec(),
+			)];
+			tokens.extend(members.iter().map(|(_, value)| value.eip712_token()));
+			tokens.extend([
+				ethabi::Token::Uint(nonce.into()),
+				ethabi::Token::Uint(valid_until.saturated_into::<u64>().into()),
+				ethabi::Token::FixedBytes(genesis_hash.as_ref().to_vec()),
+				ethabi::Token::Uint(runtime_version.spec_version.into()),
+				ethabi::Token::Uint(runtime_version.transaction_version.into()),
+			]);
+			sp_io::hashing::keccak_256(&ethabi::encode(&tokens))
+		}
+
+		/// Hashes the struct hash of a typed data with the domain separator, the result is what
+		/// the wallet actually signs.
+		pub(crate) fn eip712_typed_data_hash(
+			domain: &EIP712DomainParams,
+			struct_hash: &Keccak256Signature,
+		) -> Keccak256Signature {
+			use alloc::vec;
+
+			// TODO: will refactor this in Kevin's way for performance.
+			let domain_separator = domain.separator(T::EIP712Salt::get());
 
 			let typed_data_hash_input = &vec![
 				crate::encode::SolidityDataType::String("\x19\x01"),
 				crate::encode::SolidityDataType::Bytes(&domain_separator),
-				crate::encode::SolidityDataType::Bytes(&message_hash),
+				crate::encode::SolidityDataType::Bytes(struct_hash),
 			];
 			let bytes = crate::encode::abi::encode_packed(typed_data_hash_input);
 			sp_io::hashing::keccak_256(bytes.as_slice())
@@ -66,7 +66,8 @@ impl Get<frame_system::limits::BlockWeights> for BlockWeights {
 				weights.base_extrinsic = ExtrinsicBaseWeight::get();
 			})
 			.for_class(DispatchClass::non_mandatory(), |weights| {
-				weights.max_total = Weight::from_parts(1024, u64::MAX).into();
+				// The unsigned meta-calls must fit in `MaxMetaCallBlockShare` of the block
+				weights.max_total = Weight::from_parts(1024 * 1024 * 1024 * 1024, u64::MAX).into();
 			})
 			.build_or_panic()
 	}
@@ -172,7 +173,78 @@ parameter_types! {
 	pub EIP712Name: Vec<u8> = b"Substrate".to_vec();
 	pub EIP712Version: Vec<u8> = b"1".to_vec();
 	pub EIP712ChainID: crate::EIP712ChainID = sp_core::U256::from(0);
+	pub(crate) static EIP712ExtraChainIDs: Vec<crate::EIP712ChainID> = vec![];
 	pub EIP712VerifyingContractAddress: crate::EIP712VerifyingContractAddress = sp_core::H160::from([0u8; 20]);
+	pub(crate) static EIP712Salt: Option<crate::EIP712Salt> = None;
+}
+
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum MockCallTemplate {
+	Transfer { to: AccountId, amount: Balance },
+	Remark { text: Vec<u8> },
+}
+
+pub struct MockCallTemplates;
+impl crate::CallTemplates<RuntimeCall> for MockCallTemplates {
+	type Template = MockCallTemplate;
+
+	fn members(
+		template: &MockCallTemplate,
+	) -> (&'static str, Vec<(&'static str, crate::TemplateValue)>) {
+		use sp_core::crypto::Ss58Codec;
+		match template {
+			MockCallTemplate::Transfer { to, amount } => (
+				"Transfer",
+				vec![
+					("to", crate::TemplateValue::String(to.to_ss58check().into_bytes())),
+					("amount", crate::TemplateValue::Uint((*amount).into())),
+				],
+			),
+			MockCallTemplate::Remark { text } =>
+				("Remark", vec![("text", crate::TemplateValue::String(text.clone()))]),
+		}
+	}
+
+	fn into_call(template: MockCallTemplate) -> Option<RuntimeCall> {
+		match template {
+			MockCallTemplate::Transfer { to, amount } =>
+				Some(RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+					dest: to,
+					value: amount,
+				})),
+			MockCallTemplate::Remark { text } =>
+				Some(RuntimeCall::System(frame_system::Call::remark_with_event { remark: text })),
+		}
+	}
+}
+
+parameter_types! {
+	pub static FreeCallsPerPeriod: u32 = 0;
+	pub static MaxFreeCallsPerPeriod: u32 = u32::MAX;
+	/// The meta-calls below this nonce can only be balance calls.
+	pub static BalancesOnlyBelowNonce: u64 = 0;
+	pub static MaxMetaCallsPerPeriod: u32 = 0;
+	pub static MaxMetaCallBlockShare: sp_runtime::Perbill = sp_runtime::Perbill::one();
+	pub const RelayerServiceFeeShare: sp_runtime::Perbill = sp_runtime::Perbill::from_percent(50);
+}
+
+pub struct MockFreeCallFilter;
+impl frame_support::traits::Contains<RuntimeCall> for MockFreeCallFilter {
+	fn contains(call: &RuntimeCall) -> bool {
+		matches!(call, RuntimeCall::System(_))
+	}
+}
+
+pub struct MockMetaCallFilter;
+impl crate::MetaCallFilter<AccountId, RuntimeCall> for MockMetaCallFilter {
+	fn contains(_who: &AccountId, call: &RuntimeCall, nonce_key: u64, nonce: u64) -> bool {
+		(nonce_key == 0 && nonce >= BalancesOnlyBelowNonce::get()) ||
+			matches!(call, RuntimeCall::Balances(_))
+	}
+
+	fn can_cancel(_who: &AccountId, nonce_key: u64, next_nonce: u64, _up_to: u64) -> bool {
+		nonce_key != 0 || next_nonce >= BalancesOnlyBelowNonce::get()
+	}
 }
 
 impl pallet_evm_account_mapping::Config for Test {
@@ -182,11 +254,33 @@ impl pallet_evm_account_mapping::Config for Test {
 	type AddressConverter = pallet_evm_account_mapping::SubstrateAddressConverter;
 	type ServiceFee = ConstU128<1000>;
 	type OnUnbalancedForServiceFee = ();
-	type CallFilter = frame_support::traits::Everything;
+	type CallFilter = MockMetaCallFilter;
+	type CallDescriber = crate::CallMetadataDescriber;
+	type CallTemplates = MockCallTemplates;
+	type MaxBatchCalls = ConstU32<4>;
+	type MaxPaymasterCalls = ConstU32<4>;
+	type PaymasterDeposit = ConstU128<CENTS>;
+	type PaymasterUserDeposit = ConstU128<MILLI_CENTS>;
+	type MaxPaymasterUsers = ConstU32<2>;
+	type RelayerServiceFeeShare = RelayerServiceFeeShare;
+	type MaxBundleItems = ConstU32<4>;
+	type FreeCallFilter = MockFreeCallFilter;
+	type FreeCallsPerPeriod = FreeCallsPerPeriod;
+	type MaxFreeCallsPerPeriod = MaxFreeCallsPerPeriod;
+	type FreeCallPeriod = ConstU64<10>;
+	type FreeCallBalanceThreshold = ConstU128<CENTS>;
+	type MaxMetaCallsPerPeriod = MaxMetaCallsPerPeriod;
+	type MaxNonceLanes = ConstU32<4>;
+	type MetaCallRatePeriod = ConstU64<10>;
+	type MaxMetaCallBlockShare = MaxMetaCallBlockShare;
 	type EIP712Name = EIP712Name;
 	type EIP712Version = EIP712Version;
 	type EIP712ChainID = EIP712ChainID;
+	type EIP712ExtraChainIDs = EIP712ExtraChainIDs;
 	type EIP712VerifyingContractAddress = EIP712VerifyingContractAddress;
+	type EIP712Salt = EIP712Salt;
+	type EIP712DomainOrigin = frame_system::EnsureRoot<AccountId>;
+	type AdminOrigin = frame_system::EnsureRoot<AccountId>;
 	type WeightInfo = ();
 }
 
@@ -198,7 +292,13 @@ pub(crate) fn set_balance(who: AccountId, new_free: Balance) {
 
 // Build genesis storage according to the mock runtime.
 pub(crate) fn new_test_ext() -> sp_io::TestExternalities {
-	frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut ext: sp_io::TestExternalities =
+		frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into();
+	// The benchmarks sign the meta-calls with the keys of the keystore
+	ext.register_extension(sp_keystore::KeystoreExt::new(
+		sp_keystore::testing::MemoryKeystore::new(),
+	));
+	ext
 }
 
 pub(crate) fn run_to_block(n: u64) {
@@ -206,7 +306,9 @@ pub(crate) fn run_to_block(n: u64) {
 	assert!(n > current_block);
 	while System::block_number() < n {
 		Balances::on_finalize(System::block_number());
+		EvmAccountMapping::on_finalize(System::block_number());
 		System::set_block_number(System::block_number() + 1);
 		Balances::on_initialize(System::block_number());
+		EvmAccountMapping::on_initialize(System::block_number());
 	}
 }
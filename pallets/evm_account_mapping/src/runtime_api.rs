@@ -0,0 +1,106 @@
+// This file is part of EVM Account Mapping Pallet.
+
+// Copyright (C) HashForest Technology Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API definition for the EVM account mapping pallet.
+
+use crate::{
+	CallDescription, EIP712DomainParams, EIP712Salt, Keccak256Signature, MetaCallTerms, Nonce,
+	NonceKey, TemplateMembers, TypedDataContext,
+};
+use alloc::vec::Vec;
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+	/// Saves clients from reimplementing the nonce lookup, the address conversion and the
+	/// EIP-712 hashing.
+	pub trait EvmAccountMappingApi<AccountId, Balance, BlockNumber>
+	where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// The nonce of the next meta-call of the account.
+		fn account_nonce(who: AccountId) -> Nonce;
+
+		/// The nonce of the next meta-call of the account in the lane `nonce_key`.
+		fn lane_nonce(who: AccountId, nonce_key: NonceKey) -> Nonce;
+
+		/// The account mapped from the EVM public key, in the form required by the
+		/// `AddressConverter`.
+		fn account_of(evm_public_key: Vec<u8>) -> Option<AccountId>;
+
+		/// The account mapped from the EVM address, only some `AddressConverter`s support it.
+		fn account_of_address(evm_address: sp_core::H160) -> Option<AccountId>;
+
+		/// The message hash of the legacy `SubstrateCall` typed data, `call_data` is the encoded
+		/// call.
+		fn eip712_message_hash(who: AccountId, call_data: Vec<u8>, nonce: Nonce) -> Keccak256Signature;
+
+		/// The message hash of the v2 `SubstrateCall` typed data, `call_data` is the encoded call.
+		fn eip712_message_hash_v2(
+			who: AccountId,
+			call_data: Vec<u8>,
+			nonce: Nonce,
+			terms: MetaCallTerms<Balance, BlockNumber>,
+		) -> Keccak256Signature;
+
+		/// The message hash of the `SubstrateBatch` typed data, `calls` are the encoded calls.
+		fn eip712_batch_message_hash(
+			who: AccountId,
+			calls: Vec<Vec<u8>>,
+			nonce: Nonce,
+			atomic: bool,
+			max_fee: Balance,
+			valid_until: BlockNumber,
+		) -> Keccak256Signature;
+
+		/// The message hash of the `SponsoredSubstrateCall` typed data, `sponsor` is `None` if any
+		/// sponsor may submit it.
+		fn eip712_sponsored_message_hash(
+			who: AccountId,
+			call_data: Vec<u8>,
+			nonce: Nonce,
+			valid_until: BlockNumber,
+			sponsor: Option<AccountId>,
+		) -> Keccak256Signature;
+
+		/// The message hash of the `CancelMetaNonce` typed data.
+		fn eip712_cancel_message_hash(
+			who: AccountId,
+			nonce_key: NonceKey,
+			up_to: Nonce,
+			valid_until: BlockNumber,
+		) -> Keccak256Signature;
+
+		/// The description of the encoded call in the v2 typed data, `None` if it can't be
+		/// decoded.
+		fn describe_call(call_data: Vec<u8>) -> Option<CallDescription>;
+
+		/// The primary type name and the members of the encoded call template of
+		/// `meta_call_template`, `None` if it can't be decoded.
+		fn template_members(template: Vec<u8>) -> Option<TemplateMembers>;
+
+		/// The active EIP-712 domain.
+		fn eip712_domain() -> EIP712DomainParams;
+
+		/// The salt of the EIP-712 domain.
+		fn eip712_salt() -> Option<EIP712Salt>;
+
+		/// Everything besides the message a client needs to build the typed data.
+		fn typed_data_context() -> TypedDataContext;
+	}
+}
@@ -17,12 +17,34 @@
 
 #[allow(unused)]
 use crate::{mock::*, Error, Event};
-use codec::Decode;
-use frame_support::assert_ok;
+use codec::{Decode, Encode};
+use frame_support::{assert_noop, assert_ok, traits::Get};
 
-use sp_core::crypto::Ss58Codec;
-use sp_runtime::traits::TrailingZeroInput;
-use crate::AddressConversion;
+use sp_core::{
+	crypto::{ByteArray, Ss58Codec},
+	ecdsa, Pair,
+};
+use sp_runtime::{
+	traits::{TrailingZeroInput, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+};
+use crate::{AddressConversion, MetaCallTerms, NonceKey};
+
+fn test_pair() -> (ecdsa::Pair, AccountId) {
+	let pair = ecdsa::Pair::from_seed(&[1u8; 32]);
+	let account = crate::SubstrateAddressConverter::try_convert(&pair.public().to_raw_vec())
+		.expect("Convertable");
+	(pair, account)
+}
+
+fn sign(pair: &ecdsa::Pair, message_hash: &[u8; 32]) -> [u8; 65] {
+	let signature = pair.sign_prehashed(message_hash);
+	AsRef::<[u8]>::as_ref(&signature).try_into().expect("Valid")
+}
+
+fn remark_call() -> RuntimeCall {
+	RuntimeCall::System(frame_system::Call::remark_with_event { remark: b"Hello".to_vec() })
+}
 
 #[test]
 fn it_works() {
@@ -56,6 +78,2430 @@ fn it_works() {
 	});
 }
 
+#[test]
+fn meta_call_rejects_unsigned_tip() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), 0);
+		let signature = sign(&pair, &message_hash);
+
+		let unsigned_call = crate::Call::meta_call {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			tip: Some(1),
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(crate::MetaCallValidityError::UnsignedTip.into())
+		);
+		assert_noop!(
+			EvmAccountMapping::meta_call(RuntimeOrigin::none(), account, Box::new(call), 0, signature, Some(1)),
+			Error::<Test>::UnsignedTip
+		);
+	});
+}
+
+#[test]
+fn meta_call_v2_respects_max_fee() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+
+		// The signature covers the tip, so it can't be inflated
+		let terms = MetaCallTerms { tip: 1, ..terms(0) };
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			terms: MetaCallTerms { tip: DOLLARS / 4, ..terms.clone() },
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		// The service fee alone exceeds the bound
+		let terms = MetaCallTerms { max_fee: 1, ..self::terms(0) };
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			terms: terms.clone(),
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(crate::MetaCallValidityError::MaxFeeExceeded.into())
+		);
+		assert_noop!(
+			EvmAccountMapping::meta_call_v2(
+				RuntimeOrigin::none(),
+				account.clone(),
+				Box::new(call.clone()),
+				0,
+				signature,
+				terms
+			),
+			Error::<Test>::MaxFeeExceeded
+		);
+
+		let terms = MetaCallTerms { tip: 1, ..self::terms(0) };
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			terms: terms.clone(),
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		assert_ok!(EvmAccountMapping::meta_call_v2(
+			RuntimeOrigin::none(),
+			account,
+			Box::new(call),
+			0,
+			signature,
+			terms
+		));
+	});
+}
+
+#[test]
+fn meta_call_v2_respects_validity_window() {
+	new_test_ext().execute_with(|| {
+		run_to_block(10);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let validate = |terms: MetaCallTerms<Balance, u64>| {
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+			let unsigned_call = crate::Call::meta_call_v2 {
+				who: account.clone(),
+				call: Box::new(call.clone()),
+				nonce: 0,
+				signature: sign(&pair, &message_hash),
+				terms,
+			};
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call)
+		};
+		let terms =
+			|valid_after, valid_until| MetaCallTerms { valid_after, valid_until, ..terms(0) };
+
+		assert_eq!(validate(terms(0, 9)), Err(InvalidTransaction::Stale.into()));
+		assert_eq!(validate(terms(11, 0)), Err(InvalidTransaction::Future.into()));
+		assert_eq!(validate(terms(0, 0)).expect("Valid").longevity, crate::DEFAULT_META_CALL_LONGEVITY);
+		assert_eq!(validate(terms(5, 14)).expect("Valid").longevity, 4);
+
+		let terms = terms(0, 10);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+		run_to_block(11);
+		assert_noop!(
+			EvmAccountMapping::meta_call_v2(
+				RuntimeOrigin::none(),
+				account,
+				Box::new(call),
+				0,
+				signature,
+				terms
+			),
+			Error::<Test>::MetaCallExpired
+		);
+	});
+}
+
+#[test]
+fn meta_call_v2_is_bound_to_genesis_hash() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let terms = terms(0);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account,
+			call: Box::new(call),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			terms,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+
+		// Pretend it is another chain
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+	});
+}
+
+#[test]
+fn meta_call_v2_rederives_call_description() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let terms = terms(0);
+		assert_eq!(
+			EvmAccountMapping::describe_call(&call.encode()),
+			Some(crate::CallDescription {
+				pallet_name: b"System".to_vec(),
+				call_name: b"remark_with_event".to_vec(),
+				call_args: vec![],
+			})
+		);
+
+		// The relayer shows the user a description of another call
+		let forged_description = crate::CallDescription {
+			pallet_name: b"System".to_vec(),
+			call_name: b"remark".to_vec(),
+			call_args: vec![],
+		};
+		let struct_hash = EvmAccountMapping::eip712_struct_hash_v2(
+			account.clone(),
+			&call.encode(),
+			&forged_description,
+			0,
+			&terms,
+		);
+		let message_hash = EvmAccountMapping::eip712_typed_data_hash(
+			&crate::CurrentEIP712Domain::<Test>::get(),
+			&struct_hash,
+		);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			terms: terms.clone(),
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account,
+			call: Box::new(call),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			terms,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+	});
+}
+
+#[test]
+fn nonce_lanes_are_independent() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let meta_call = |nonce_key: u64, nonce: u64| {
+			let call = remark_call();
+			let terms = terms(nonce_key);
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), nonce, &terms);
+			crate::Call::<Test>::meta_call_v2 {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce,
+				signature: sign(&pair, &message_hash),
+				terms,
+			}
+		};
+		let dispatch = |unsigned_call: crate::Call<Test>| {
+			let crate::Call::meta_call_v2 { who, call, nonce, signature, terms } = unsigned_call else {
+				unreachable!()
+			};
+			EvmAccountMapping::meta_call_v2(RuntimeOrigin::none(), who, call, nonce, signature, terms)
+		};
+
+		// Every lane has its own tags
+		let lane_0 =
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(0, 0))
+				.expect("Valid");
+		let lane_1 =
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(1, 0))
+				.expect("Valid");
+		// Lane zero keeps the tags of the sequential nonce
+		assert_eq!(
+			lane_0.provides,
+			vec![("EVMAccountMapping", (&account, 0u64).encode()).encode()]
+		);
+		assert_ne!(lane_0.provides, lane_1.provides);
+
+		// Lane 1 goes ahead while lane 0 is stuck
+		assert_ok!(dispatch(meta_call(1, 0)));
+		assert_ok!(dispatch(meta_call(1, 1)));
+		assert_eq!(EvmAccountMapping::lane_nonce(&account, 1), 2);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(1, 1)),
+			Err(InvalidTransaction::Stale.into())
+		);
+
+		// Lane 0 is the nonce of the other typed data
+		assert_ok!(dispatch(meta_call(0, 0)));
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+		assert_eq!(EvmAccountMapping::lane_nonce(&account, 0), 1);
+		assert_eq!(EvmAccountMapping::lane_nonce(&account, 2), 0);
+
+		// The lane is signed
+		let crate::Call::meta_call_v2 { who, call, nonce, signature, terms } = meta_call(2, 0) else {
+			unreachable!()
+		};
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who,
+			call,
+			nonce,
+			signature,
+			terms: MetaCallTerms { nonce_key: 3, ..terms },
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+	});
+}
+
+/// Applies an unsigned extrinsic like a block author including it, `pre_dispatch` runs before the
+/// call is dispatched.
+fn apply_unsigned(
+	call: crate::Call<Test>,
+) -> sp_runtime::ApplyExtrinsicResultWithInfo<frame_support::dispatch::PostDispatchInfo> {
+	use frame_support::dispatch::GetDispatchInfo;
+	use sp_runtime::traits::Applyable;
+	let call = RuntimeCall::EvmAccountMapping(call);
+	let info = call.get_dispatch_info();
+	let len = call.encoded_size();
+	sp_runtime::generic::CheckedExtrinsic::<
+		AccountId,
+		RuntimeCall,
+		frame_system::CheckNonZeroSender<Test>,
+	> {
+		signed: None,
+		function: call,
+	}
+	.apply::<Test>(&info, len)
+}
+
+#[test]
+fn block_author_cannot_include_invalid_meta_calls() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		let meta_call = |call: RuntimeCall, signed_call: RuntimeCall, nonce: u64| {
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash(account.clone(), &signed_call.encode(), nonce);
+			crate::Call::<Test>::meta_call {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce,
+				signature: sign(&pair, &message_hash),
+				tip: None,
+			}
+		};
+		let other_call =
+			RuntimeCall::System(frame_system::Call::remark_with_event { remark: b"Bye".to_vec() });
+
+		// Underfunded
+		assert_eq!(
+			apply_unsigned(meta_call(remark_call(), remark_call(), 0)),
+			Err(crate::MetaCallValidityError::InsufficientBalanceForFees.into())
+		);
+
+		set_balance(account.clone(), DOLLARS);
+		// The signature is made for another call
+		assert_eq!(
+			apply_unsigned(meta_call(other_call, remark_call(), 0)),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+		// The nonce is ahead
+		assert_eq!(
+			apply_unsigned(meta_call(remark_call(), remark_call(), 1)),
+			Err(InvalidTransaction::Future.into())
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+
+		// The account is drained after the meta-call entered the pool
+		let unsigned_call = meta_call(remark_call(), remark_call(), 0);
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		set_balance(account.clone(), 0);
+		assert_eq!(apply_unsigned(unsigned_call.clone()), Err(crate::MetaCallValidityError::InsufficientBalanceForFees.into()));
+
+		set_balance(account.clone(), DOLLARS);
+		assert_ok!(apply_unsigned(unsigned_call.clone()).expect("Included"));
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+
+		// Can't be included twice
+		assert_eq!(apply_unsigned(unsigned_call), Err(InvalidTransaction::Stale.into()));
+	});
+}
+
+/// A `meta_call` of `remark_call` signed by `pair`.
+fn remark_meta_call(pair: &ecdsa::Pair, who: &AccountId, nonce: u64) -> crate::Call<Test> {
+	let message_hash =
+		EvmAccountMapping::eip712_message_hash(who.clone(), &remark_call().encode(), nonce);
+	crate::Call::meta_call {
+		who: who.clone(),
+		call: Box::new(remark_call()),
+		nonce,
+		signature: sign(pair, &message_hash),
+		tip: None,
+	}
+}
+
+#[test]
+fn meta_calls_are_rate_limited() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		MaxMetaCallsPerPeriod::set(2);
+		let meta_call = |nonce: u64| remark_meta_call(&pair, &account, nonce);
+		let validate = |nonce: u64| {
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(nonce))
+		};
+
+		// The pending meta-calls take a slot too
+		assert_ok!(validate(0));
+		assert_ok!(validate(1));
+		assert_eq!(validate(2), Err(crate::MetaCallValidityError::RateLimited.into()));
+
+		assert_ok!(apply_unsigned(meta_call(0)).expect("Included"));
+		assert_ok!(apply_unsigned(meta_call(1)).expect("Included"));
+		assert_eq!(crate::MetaCallsInPeriod::<Test>::get(0, &account), 2);
+		assert_eq!(validate(2), Err(crate::MetaCallValidityError::RateLimited.into()));
+		assert_eq!(
+			apply_unsigned(meta_call(2)),
+			Err(crate::MetaCallValidityError::RateLimited.into())
+		);
+
+		// A new period starts every 10 blocks, the counts of the past one are removed
+		run_to_block(10);
+		assert_eq!(crate::MetaCallsInPeriod::<Test>::iter_prefix(0).count(), 0);
+		assert_eq!(crate::OldestMetaCallPeriod::<Test>::get(), Some(1));
+		assert_ok!(validate(2));
+		MaxMetaCallsPerPeriod::set(0);
+	});
+}
+
+#[test]
+fn meta_calls_cant_flood_the_pool_over_many_lanes() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		MaxMetaCallsPerPeriod::set(2);
+		let meta_call = |nonce_key: u64| {
+			let call = remark_call();
+			let terms = terms(nonce_key);
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+			crate::Call::<Test>::meta_call_v2 {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce: 0,
+				signature: sign(&pair, &message_hash),
+				terms,
+			}
+		};
+
+		// Nonce 0 of every lane is valid, but the lanes are capped by `MaxNonceLanes`
+		let max_lanes: u64 = <<Test as crate::Config>::MaxNonceLanes as Get<u32>>::get().into();
+		for nonce_key in 0..max_lanes {
+			assert_ok!(EvmAccountMapping::validate_unsigned(
+				TransactionSource::External,
+				&meta_call(nonce_key)
+			));
+		}
+		for nonce_key in [max_lanes, max_lanes + 1, u64::MAX] {
+			assert_eq!(
+				EvmAccountMapping::validate_unsigned(
+					TransactionSource::External,
+					&meta_call(nonce_key)
+				),
+				Err(InvalidTransaction::ExhaustsResources.into())
+			);
+		}
+
+		// Nor dispatched by a signed submission
+		let crate::Call::meta_call_v2 { who, call, nonce, signature, terms } = meta_call(max_lanes)
+		else {
+			unreachable!()
+		};
+		assert_noop!(
+			EvmAccountMapping::meta_call_v2(RuntimeOrigin::none(), who, call, nonce, signature, terms),
+			Error::<Test>::InvalidNonceLane
+		);
+		MaxMetaCallsPerPeriod::set(0);
+	});
+}
+
+#[test]
+fn meta_calls_take_a_bounded_share_of_the_block() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		MaxMetaCallBlockShare::set(sp_runtime::Perbill::from_percent(50));
+		let meta_call = |nonce: u64| remark_meta_call(&pair, &account, nonce);
+		let (max_weight, max_len) = EvmAccountMapping::meta_call_block_limits();
+
+		assert_ok!(apply_unsigned(meta_call(0)).expect("Included"));
+		let (used_weight, used_len) = crate::MetaCallBlockUsage::<Test>::get();
+		assert!(used_weight.any_gt(frame_support::weights::Weight::zero()) && used_len > 0);
+
+		// The meta-calls of the block have taken their share of the weight or the length
+		crate::MetaCallBlockUsage::<Test>::put((max_weight, 0));
+		assert_eq!(apply_unsigned(meta_call(1)), Err(InvalidTransaction::ExhaustsResources.into()));
+		crate::MetaCallBlockUsage::<Test>::put((frame_support::weights::Weight::zero(), max_len));
+		assert_eq!(apply_unsigned(meta_call(1)), Err(InvalidTransaction::ExhaustsResources.into()));
+
+		// It stays in the pool and is included in the next block
+		assert_ok!(EvmAccountMapping::validate_unsigned(
+			TransactionSource::External,
+			&meta_call(1)
+		));
+		run_to_block(2);
+		assert_eq!(
+			crate::MetaCallBlockUsage::<Test>::get(),
+			(frame_support::weights::Weight::zero(), 0)
+		);
+		assert_ok!(apply_unsigned(meta_call(1)).expect("Included"));
+
+		// A meta-call which can't fit in any block is rejected by the pool
+		MaxMetaCallBlockShare::set(sp_runtime::Perbill::zero());
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(2)),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+		MaxMetaCallBlockShare::set(sp_runtime::Perbill::one());
+	});
+}
+
+#[test]
+fn higher_tip_raises_the_priority() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let validate_v2 = |call: RuntimeCall, tip: Balance| {
+			let terms = MetaCallTerms { tip, ..terms(0) };
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+			let unsigned_call = crate::Call::meta_call_v2 {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce: 0,
+				signature: sign(&pair, &message_hash),
+				terms,
+			};
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call)
+				.expect("Valid")
+		};
+		let heavy_call = RuntimeCall::System(frame_system::Call::remark_with_event {
+			remark: vec![0u8; 1024],
+		});
+
+		let pending = validate_v2(remark_call(), 100);
+
+		// The same nonce provides the same tag, the priority grows with the tip
+		let higher_tip = validate_v2(remark_call(), 101);
+		assert_eq!(higher_tip.provides, pending.provides);
+		assert!(higher_tip.priority > pending.priority);
+		assert!(validate_v2(remark_call(), 99).priority < pending.priority);
+
+		// Like signed extrinsics, the priority is scaled by the size of the call
+		assert!(validate_v2(heavy_call, 100).priority < pending.priority);
+	});
+}
+
+#[test]
+fn cancel_meta_nonce_invalidates_signed_calls() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), 1);
+		let unsigned_call = crate::Call::meta_call {
+			who: account.clone(),
+			call: Box::new(call),
+			nonce: 1,
+			signature: sign(&pair, &message_hash),
+			tip: None,
+		};
+
+		assert_ok!(EvmAccountMapping::cancel_meta_nonce(RuntimeOrigin::signed(account.clone()), 0, 1));
+		System::assert_last_event(
+			Event::MetaNonceCancelled { who: account.clone(), nonce_key: 0, next_nonce: 2 }.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 2);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::Stale.into())
+		);
+
+		// The nonce can't go backwards
+		assert_noop!(
+			EvmAccountMapping::cancel_meta_nonce(RuntimeOrigin::signed(account), 0, 1),
+			Error::<Test>::NonceError
+		);
+	});
+}
+
+#[test]
+fn meta_cancel_nonce_closes_a_lane() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let message_hash =
+			EvmAccountMapping::eip712_cancel_message_hash(account.clone(), 1, u64::MAX, 5);
+		let typed_data = crate::typed_data::build_cancel(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			1,
+			u64::MAX,
+			5,
+		);
+		// `u64::MAX` isn't a safe integer in JavaScript
+		assert_eq!(typed_data["message"]["upTo"], u64::MAX.to_string());
+		assert_eq!(hash_typed_data_json(&typed_data), message_hash);
+		let signature = sign(&pair, &message_hash);
+
+		// The signature covers the nonce and the validity window
+		let unsigned_call = crate::Call::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: 1,
+			up_to: 5,
+			valid_until: 5,
+			signature,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+		let unsigned_call = crate::Call::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: 1,
+			up_to: u64::MAX,
+			valid_until: 0,
+			signature,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		let unsigned_call = crate::Call::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: 1,
+			up_to: u64::MAX,
+			valid_until: 5,
+			signature,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		assert_ok!(EvmAccountMapping::meta_cancel_nonce(
+			RuntimeOrigin::none(),
+			account.clone(),
+			1,
+			u64::MAX,
+			5,
+			signature
+		));
+		System::assert_has_event(
+			Event::ServiceFeePaid { who: account.clone(), actual_fee: 1000, expected_fee: 1000 }
+				.into(),
+		);
+		System::assert_has_event(
+			Event::MetaNonceCancelled { who: account.clone(), nonce_key: 1, next_nonce: u64::MAX }
+				.into(),
+		);
+		// It pays the transaction fee too
+		assert!(System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::EvmAccountMapping(Event::TransactionFeePaid { actual_fee, .. })
+				if actual_fee > 0
+		)));
+		assert!(Balances::free_balance(&account) < DOLLARS - 1000);
+
+		// The lane is closed, other lanes still work
+		assert_eq!(EvmAccountMapping::lane_nonce(&account, 1), u64::MAX);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		let call = remark_call();
+		let terms = terms(1);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), u64::MAX, &terms);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call),
+			nonce: u64::MAX,
+			signature: sign(&pair, &message_hash),
+			terms,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_ok!(EvmAccountMapping::cancel_meta_nonce(RuntimeOrigin::signed(account.clone()), 2, 0));
+
+		// A cancellation expires after `valid_until`
+		let message_hash = EvmAccountMapping::eip712_cancel_message_hash(account.clone(), 3, 0, 5);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: 3,
+			up_to: 0,
+			valid_until: 5,
+			signature,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		run_to_block(6);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_noop!(
+			EvmAccountMapping::meta_cancel_nonce(RuntimeOrigin::none(), account, 3, 0, 5, signature),
+			Error::<Test>::MetaCallExpired
+		);
+	});
+}
+
+#[test]
+fn cancellations_cant_open_unbounded_lanes() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let max_lanes: u64 = <<Test as crate::Config>::MaxNonceLanes as Get<u32>>::get().into();
+
+		assert_ok!(EvmAccountMapping::cancel_meta_nonce(
+			RuntimeOrigin::signed(account.clone()),
+			max_lanes - 1,
+			0
+		));
+		assert_noop!(
+			EvmAccountMapping::cancel_meta_nonce(
+				RuntimeOrigin::signed(account.clone()),
+				max_lanes,
+				0
+			),
+			Error::<Test>::InvalidNonceLane
+		);
+
+		let message_hash =
+			EvmAccountMapping::eip712_cancel_message_hash(account.clone(), max_lanes, 0, 0);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: max_lanes,
+			up_to: 0,
+			valid_until: 0,
+			signature,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+		assert_noop!(
+			EvmAccountMapping::meta_cancel_nonce(
+				RuntimeOrigin::none(),
+				account.clone(),
+				max_lanes,
+				0,
+				0,
+				signature
+			),
+			Error::<Test>::InvalidNonceLane
+		);
+		assert_eq!(crate::LaneNonce::<Test>::iter_prefix(&account).count(), 1);
+	});
+}
+
+#[test]
+fn eip712_salt_separates_domains() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), 0);
+		let unsigned_call = crate::Call::meta_call {
+			who: account,
+			call: Box::new(call),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			tip: None,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		assert_eq!(
+			<crate::GenesisHashSalt<Test> as Get<_>>::get(),
+			Some([1u8; 32])
+		);
+		EIP712Salt::set(<crate::GenesisHashSalt<Test> as Get<_>>::get());
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+		EIP712Salt::set(None);
+	});
+}
+
+#[test]
+fn set_eip712_domain_with_grace_period() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), 0);
+		let unsigned_call = crate::Call::meta_call {
+			who: account,
+			call: Box::new(call),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			tip: None,
+		};
+
+		let domain = crate::EIP712DomainParams {
+			name: b"Substrate".to_vec(),
+			version: b"1".to_vec(),
+			chain_id: sp_core::U256::from(1),
+			verifying_contract: sp_core::H160::from([0u8; 20]),
+		};
+		assert_noop!(
+			EvmAccountMapping::set_eip712_domain(RuntimeOrigin::none(), domain.clone(), Some(5)),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(EvmAccountMapping::set_eip712_domain(RuntimeOrigin::root(), domain.clone(), Some(5)));
+		System::assert_last_event(
+			Event::EIP712DomainUpdated { domain: domain.clone(), previous_valid_until: Some(6) }.into(),
+		);
+		assert_eq!(crate::CurrentEIP712Domain::<Test>::get(), domain);
+
+		// The previous domain is still accepted in the grace period
+		run_to_block(6);
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		run_to_block(7);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(crate::MetaCallValidityError::DomainMismatch.into())
+		);
+
+		// No grace period, the replaced domain is rejected immediately
+		assert_ok!(EvmAccountMapping::set_eip712_domain(
+			RuntimeOrigin::root(),
+			crate::DefaultEIP712Domain::<Test>::get(),
+			None
+		));
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		assert_ok!(EvmAccountMapping::set_eip712_domain(RuntimeOrigin::root(), domain, None));
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+	});
+}
+
+#[test]
+fn rejected_meta_calls_have_distinct_validity_errors() {
+	use crate::MetaCallValidityError;
+
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), 0);
+		let meta_call = |signature: [u8; 65]| crate::Call::meta_call {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			tip: None,
+		};
+		let validate = |unsigned_call: &crate::Call<Test>| {
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, unsigned_call)
+		};
+
+		let mut signature = sign(&pair, &message_hash);
+		assert_ok!(validate(&meta_call(signature)));
+		// The recovery ID is out of range
+		signature[64] = 5;
+		assert_eq!(
+			validate(&meta_call(signature)),
+			Err(MetaCallValidityError::BadSignatureEncoding.into())
+		);
+		// r and s are out of range
+		assert_eq!(
+			validate(&meta_call([0xff; 65])),
+			Err(MetaCallValidityError::BadSignatureEncoding.into())
+		);
+		// Signed by another key
+		let (other_pair, _) = ecdsa::Pair::generate();
+		assert_eq!(
+			validate(&meta_call(sign(&other_pair, &message_hash))),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+		assert_eq!(
+			validate(&crate::Call::set_eip712_domain {
+				domain: crate::CurrentEIP712Domain::<Test>::get(),
+				grace_period: None,
+			}),
+			Err(MetaCallValidityError::UnsupportedCall.into())
+		);
+
+		// The codes can be decoded by clients
+		for (code, error) in MetaCallValidityError::ALL.into_iter().enumerate() {
+			assert_eq!(error.code() as usize, code);
+			assert_eq!(MetaCallValidityError::try_from(error.code()), Ok(error));
+			assert_eq!(MetaCallValidityError::from_validity_error(&error.into()), Some(error));
+		}
+		let unknown_code = MetaCallValidityError::ALL.len() as u8;
+		assert_eq!(MetaCallValidityError::try_from(unknown_code), Err(()));
+		assert_eq!(
+			MetaCallValidityError::from_validity_error(&InvalidTransaction::BadSigner.into()),
+			None
+		);
+	});
+}
+
+#[test]
+fn extra_chain_ids_are_accepted() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		// The wallet is connected to Base
+		let domain = crate::EIP712DomainParams {
+			chain_id: sp_core::U256::from(8453),
+			..crate::CurrentEIP712Domain::<Test>::get()
+		};
+		let struct_hash = EvmAccountMapping::eip712_struct_hash(account.clone(), &call.encode(), 0);
+		let message_hash = EvmAccountMapping::eip712_typed_data_hash(&domain, &struct_hash);
+		let unsigned_call = crate::Call::meta_call {
+			who: account,
+			call: Box::new(call),
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			tip: None,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		EIP712ExtraChainIDs::set(vec![sp_core::U256::from(1), sp_core::U256::from(8453)]);
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		EIP712ExtraChainIDs::set(vec![]);
+	});
+}
+
+/// Hashes JSON typed data the way `eth_signTypedData_v4` does, only the member types the pallet
+/// uses are supported.
+fn hash_typed_data_json(typed_data: &serde_json::Value) -> [u8; 32] {
+	use sp_core::U256;
+	use sp_io::hashing::keccak_256;
+
+	let decode_hex = |value: &serde_json::Value| {
+		hex::decode(value.as_str().expect("Hex string").trim_start_matches("0x")).expect("Valid")
+	};
+	let hash_struct = |type_name: &str, value: &serde_json::Value| {
+		let members = typed_data["types"][type_name].as_array().expect("Known type");
+		let encoded_type = format!(
+			"{type_name}({})",
+			members
+				.iter()
+				.map(|member| format!(
+					"{} {}",
+					member["type"].as_str().expect("Valid"),
+					member["name"].as_str().expect("Valid")
+				))
+				.collect::<Vec<_>>()
+				.join(",")
+		);
+		let mut encoded = keccak_256(encoded_type.as_bytes()).to_vec();
+		for member in members {
+			let field = &value[member["name"].as_str().expect("Valid")];
+			let mut word = [0u8; 32];
+			match member["type"].as_str().expect("Valid") {
+				"string" => word = keccak_256(field.as_str().expect("String").as_bytes()),
+				"bytes" => word = keccak_256(&decode_hex(field)),
+				"bytes32" => word.copy_from_slice(&decode_hex(field)),
+				"address" => word[12..].copy_from_slice(&decode_hex(field)),
+				"bool" => word[31] = field.as_bool().expect("Bool") as u8,
+				"bytes[]" => {
+					let hashed_items = field
+						.as_array()
+						.expect("Array")
+						.iter()
+						.flat_map(|item| keccak_256(&decode_hex(item)))
+						.collect::<Vec<_>>();
+					word = keccak_256(&hashed_items);
+				},
+				ty if ty.starts_with("uint") => {
+					let number = match field {
+						serde_json::Value::Number(number) =>
+							U256::from(number.as_u64().expect("Unsigned")),
+						serde_json::Value::String(number) =>
+							U256::from_dec_str(number).expect("Decimal"),
+						_ => panic!("`{ty}` must be a number or a decimal string"),
+					};
+					number.to_big_endian(&mut word);
+				},
+				ty => panic!("Unsupported type `{ty}`"),
+			}
+			encoded.extend_from_slice(&word);
+		}
+		keccak_256(&encoded)
+	};
+
+	let domain_separator = hash_struct("EIP712Domain", &typed_data["domain"]);
+	let struct_hash =
+		hash_struct(typed_data["primaryType"].as_str().expect("Valid"), &typed_data["message"]);
+	keccak_256(&[&b"\x19\x01"[..], &domain_separator, &struct_hash].concat())
+}
+
+#[test]
+fn typed_data_json_matches_message_hash() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (_, account) = test_pair();
+		let call_data = remark_call().encode();
+
+		let typed_data = crate::typed_data::build::<u128, u64>(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call_data,
+			3,
+			None,
+		);
+		assert_eq!(typed_data["primaryType"], "SubstrateCall");
+		assert_eq!(typed_data["types"]["SubstrateCall"].as_array().map(Vec::len), Some(3));
+		assert_eq!(
+			hash_typed_data_json(&typed_data),
+			EvmAccountMapping::eip712_message_hash(account.clone(), &call_data, 3)
+		);
+
+		EIP712Salt::set(Some([7u8; 32]));
+		let typed_data = crate::typed_data::build::<u128, u64>(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call_data,
+			3,
+			None,
+		);
+		assert_eq!(typed_data["domain"]["salt"], format!("0x{}", hex::encode([7u8; 32])));
+		assert_eq!(
+			hash_typed_data_json(&typed_data),
+			EvmAccountMapping::eip712_message_hash(account, &call_data, 3)
+		);
+		EIP712Salt::set(None);
+	});
+}
+
+#[test]
+fn typed_data_json_keeps_large_uint64_exact() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (_, account) = test_pair();
+		let call_data = remark_call().encode();
+		let large = (1u64 << 53) + 1;
+		let terms = MetaCallTerms { max_fee: DOLLARS, valid_until: u64::MAX, ..terms(1 << 60) };
+
+		let typed_data = crate::typed_data::build(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call_data,
+			large,
+			Some((&terms, &EvmAccountMapping::describe_call(&call_data).expect("Decodable"))),
+		);
+		assert_eq!(typed_data["message"]["nonce"], large.to_string());
+		assert_eq!(typed_data["message"]["nonceKey"], (1u64 << 60).to_string());
+		assert_eq!(typed_data["message"]["validUntil"], u64::MAX.to_string());
+		// The safe integers are still numbers
+		assert_eq!(typed_data["message"]["validAfter"], 0);
+		assert_eq!(
+			hash_typed_data_json(&typed_data),
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call_data, large, &terms)
+		);
+
+		let calls = vec![call_data];
+		let typed_data = crate::typed_data::build_batch(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&calls,
+			large,
+			false,
+			DOLLARS,
+			u64::MAX,
+		);
+		assert_eq!(typed_data["message"]["nonce"], large.to_string());
+		assert_eq!(typed_data["message"]["validUntil"], u64::MAX.to_string());
+		assert_eq!(
+			hash_typed_data_json(&typed_data),
+			EvmAccountMapping::eip712_batch_message_hash(
+				account,
+				&calls,
+				large,
+				false,
+				DOLLARS,
+				u64::MAX
+			)
+		);
+	});
+}
+
+#[test]
+fn typed_data_json_v2_matches_message_hash() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (_, account) = test_pair();
+		let call_data = remark_call().encode();
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		// Too large for a JavaScript number, so it goes as a decimal string.
+		let terms = MetaCallTerms {
+			tip: u128::MAX / 3,
+			max_fee: DOLLARS,
+			valid_after: 1,
+			valid_until: 10,
+			..terms(0)
+		};
+
+		let description = EvmAccountMapping::describe_call(&call_data).expect("Decodable");
+
+		let typed_data = crate::typed_data::build(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call_data,
+			0,
+			Some((&terms, &description)),
+		);
+		assert_eq!(typed_data["message"]["palletName"], "System");
+		assert_eq!(typed_data["message"]["callName"], "remark_with_event");
+		assert_eq!(typed_data["message"]["tip"], (u128::MAX / 3).to_string());
+		assert_eq!(
+			typed_data["message"]["genesisHash"],
+			format!("0x{}", hex::encode([1u8; 32]))
+		);
+		assert_eq!(
+			hash_typed_data_json(&typed_data),
+			EvmAccountMapping::eip712_message_hash_v2(account, &call_data, 0, &terms)
+		);
+	});
+}
+
+#[test]
+fn meta_call_template_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let recipient = AccountId::from([2u8; 32]);
+		let template = MockCallTemplate::Transfer { to: recipient.clone(), amount: DOLLARS / 10 };
+
+		let (primary_type, members) =
+			<MockCallTemplates as crate::CallTemplates<RuntimeCall>>::members(&template);
+		assert_eq!(
+			crate::template_encoded_type(primary_type, &members),
+			"Transfer(string to,uint256 amount,uint64 nonce,uint64 validUntil,bytes32 genesisHash,uint32 specVersion,uint32 txVersion)"
+		);
+		let message_hash = EvmAccountMapping::eip712_template_message_hash(&template, 0, 5);
+		let typed_data = crate::typed_data::build_template(
+			&EvmAccountMapping::typed_data_context(),
+			primary_type,
+			&members,
+			0,
+			5,
+		);
+		assert_eq!(typed_data["message"]["to"], recipient.to_ss58check());
+		assert_eq!(hash_typed_data_json(&typed_data), message_hash);
+		// The RPC builds the same typed data from the encoded template
+		let template_members =
+			EvmAccountMapping::template_members(&template.encode()).expect("Valid template");
+		assert_eq!(
+			crate::typed_data::build_template_members(
+				&EvmAccountMapping::typed_data_context(),
+				&template_members,
+				0,
+				5,
+			),
+			typed_data
+		);
+		assert_eq!(EvmAccountMapping::template_members(&[0xff]), None);
+
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_template {
+			who: account.clone(),
+			template: template.clone(),
+			nonce: 0,
+			valid_until: 5,
+			signature,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+
+		assert_ok!(EvmAccountMapping::meta_call_template(
+			RuntimeOrigin::none(),
+			account.clone(),
+			template.clone(),
+			0,
+			5,
+			signature
+		));
+		assert_eq!(Balances::free_balance(&recipient), DOLLARS / 10);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+
+		// It expires after `valid_until`
+		let message_hash = EvmAccountMapping::eip712_template_message_hash(&template, 1, 5);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_template {
+			who: account.clone(),
+			template: template.clone(),
+			nonce: 1,
+			valid_until: 5,
+			signature,
+		};
+		run_to_block(6);
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_noop!(
+			EvmAccountMapping::meta_call_template(
+				RuntimeOrigin::none(),
+				account,
+				template,
+				1,
+				5,
+				signature
+			),
+			Error::<Test>::MetaCallExpired
+		);
+	});
+}
+
+#[test]
+fn meta_call_template_signature_covers_the_template() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let remark = MockCallTemplate::Remark { text: b"Hello".to_vec() };
+		let message_hash = EvmAccountMapping::eip712_template_message_hash(&remark, 0, 0);
+
+		// The relayer swaps the template
+		let unsigned_call = crate::Call::meta_call_template {
+			who: account.clone(),
+			template: MockCallTemplate::Transfer { to: AccountId::from([2u8; 32]), amount: DOLLARS / 10 },
+			nonce: 0,
+			valid_until: 0,
+			signature: sign(&pair, &message_hash),
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		// The signature doesn't hold on another chain
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		let unsigned_call = crate::Call::meta_call_template {
+			who: account,
+			template: remark,
+			nonce: 0,
+			valid_until: 0,
+			signature: sign(&pair, &message_hash),
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+	});
+}
+
+#[test]
+fn meta_batch_call_best_effort() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let overdraft_call = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: AccountId::from([2u8; 32]),
+			value: 10 * DOLLARS,
+		});
+		let calls = vec![remark_call(), overdraft_call, remark_call()];
+		let encoded_calls = calls.iter().map(|call| call.encode()).collect::<Vec<_>>();
+		let message_hash = EvmAccountMapping::eip712_batch_message_hash(
+			account.clone(),
+			&encoded_calls,
+			0,
+			false,
+			DOLLARS / 2,
+			0,
+		);
+		let typed_data = crate::typed_data::build_batch(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&encoded_calls,
+			0,
+			false,
+			DOLLARS / 2,
+			0,
+		);
+		assert_eq!(hash_typed_data_json(&typed_data), message_hash);
+
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_batch_call {
+			who: account.clone(),
+			calls: calls.clone(),
+			nonce: 0,
+			signature,
+			atomic: false,
+			max_fee: DOLLARS / 2,
+			valid_until: 0,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+
+		// The signature covers `atomic`
+		let unsigned_atomic_call = crate::Call::meta_batch_call {
+			who: account.clone(),
+			calls: calls.clone(),
+			nonce: 0,
+			signature,
+			atomic: true,
+			max_fee: DOLLARS / 2,
+			valid_until: 0,
+		};
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_atomic_call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		assert_ok!(EvmAccountMapping::meta_batch_call(
+			RuntimeOrigin::none(),
+			account.clone(),
+			calls,
+			0,
+			signature,
+			false,
+			DOLLARS / 2,
+			0
+		));
+		let call_results = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::EvmAccountMapping(Event::BatchCallDone { index, call_result, .. }) =>
+					Some((index, call_result.is_ok())),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(call_results, vec![(0, true), (1, false), (2, true)]);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+	});
+}
+
+#[test]
+fn meta_batch_call_atomic() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let overdraft_call = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: AccountId::from([2u8; 32]),
+			value: 10 * DOLLARS,
+		});
+		let calls = vec![remark_call(), overdraft_call];
+		let encoded_calls = calls.iter().map(|call| call.encode()).collect::<Vec<_>>();
+		let message_hash = EvmAccountMapping::eip712_batch_message_hash(
+			account.clone(),
+			&encoded_calls,
+			0,
+			true,
+			DOLLARS / 2,
+			0,
+		);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_batch_call {
+			who: account.clone(),
+			calls: calls.clone(),
+			nonce: 0,
+			signature,
+			atomic: true,
+			max_fee: DOLLARS / 2,
+			valid_until: 0,
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+
+		assert_ok!(EvmAccountMapping::meta_batch_call(
+			RuntimeOrigin::none(),
+			account.clone(),
+			calls,
+			0,
+			signature,
+			true,
+			DOLLARS / 2,
+			0
+		));
+		// The remark is reverted along with its event
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::System(frame_system::Event::Remarked { .. }) |
+				RuntimeEvent::EvmAccountMapping(Event::BatchCallDone { .. })
+		)));
+		assert!(System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::EvmAccountMapping(Event::BatchInterrupted { index: 1, .. })
+		)));
+		// The fees are still paid
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+	});
+}
+
+#[test]
+fn meta_batch_call_is_bound_to_the_signed_terms() {
+	new_test_ext().execute_with(|| {
+		run_to_block(2);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let calls = vec![remark_call(), remark_call()];
+		let encoded_calls = calls.iter().map(|call| call.encode()).collect::<Vec<_>>();
+		let meta_batch_call = |max_fee: Balance, valid_until: u64| {
+			let message_hash = EvmAccountMapping::eip712_batch_message_hash(
+				account.clone(),
+				&encoded_calls,
+				0,
+				false,
+				max_fee,
+				valid_until,
+			);
+			crate::Call::<Test>::meta_batch_call {
+				who: account.clone(),
+				calls: calls.clone(),
+				nonce: 0,
+				signature: sign(&pair, &message_hash),
+				atomic: false,
+				max_fee,
+				valid_until,
+			}
+		};
+		let validate = |unsigned_call: &crate::Call<Test>| {
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, unsigned_call)
+		};
+
+		assert_ok!(validate(&meta_batch_call(DOLLARS / 2, 2)));
+		// The fees exceed the signed `maxFee`
+		assert_eq!(
+			validate(&meta_batch_call(1, 0)),
+			Err(crate::MetaCallValidityError::MaxFeeExceeded.into())
+		);
+		// The signed `validUntil` has passed
+		assert_eq!(validate(&meta_batch_call(DOLLARS / 2, 1)), Err(InvalidTransaction::Stale.into()));
+
+		// The signature can't be replayed on another chain
+		let unsigned_call = meta_batch_call(DOLLARS / 2, 0);
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		assert_eq!(validate(&unsigned_call), Err(InvalidTransaction::BadSigner.into()));
+	});
+}
+
+#[test]
+fn meta_batch_call_rejects_empty_or_oversized_batches() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		for calls in [vec![], vec![remark_call(); 5]] {
+			let encoded_calls = calls.iter().map(|call| call.encode()).collect::<Vec<_>>();
+			let message_hash = EvmAccountMapping::eip712_batch_message_hash(
+				account.clone(),
+				&encoded_calls,
+				0,
+				false,
+				DOLLARS / 2,
+				0,
+			);
+			let unsigned_call = crate::Call::meta_batch_call {
+				who: account.clone(),
+				calls,
+				nonce: 0,
+				signature: sign(&pair, &message_hash),
+				atomic: false,
+				max_fee: DOLLARS / 2,
+				valid_until: 0,
+			};
+			assert_eq!(
+				EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+				Err(crate::MetaCallValidityError::InvalidBatchSize.into())
+			);
+		}
+	});
+}
+
+#[test]
+fn sponsored_meta_call_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		// The user has no balance
+		let (pair, account) = test_pair();
+		let sponsor = AccountId::from([3u8; 32]);
+		set_balance(sponsor.clone(), DOLLARS);
+		let call = remark_call();
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		let message_hash = EvmAccountMapping::eip712_sponsored_message_hash(
+			account.clone(),
+			&call.encode(),
+			0,
+			5,
+			None,
+		);
+		let typed_data = crate::typed_data::build_sponsored(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call.encode(),
+			&EvmAccountMapping::describe_call(&call.encode()).expect("Decodable"),
+			0,
+			5,
+			None,
+		);
+		assert_eq!(typed_data["message"]["sponsor"], "");
+		assert_eq!(
+			typed_data["message"]["genesisHash"],
+			format!("0x{}", hex::encode([1u8; 32]))
+		);
+		assert_eq!(hash_typed_data_json(&typed_data), message_hash);
+		let signature = sign(&pair, &message_hash);
+
+		assert_ok!(EvmAccountMapping::sponsored_meta_call(
+			RuntimeOrigin::signed(sponsor.clone()),
+			account.clone(),
+			Box::new(call.clone()),
+			0,
+			5,
+			signature,
+			false
+		));
+		System::assert_has_event(
+			Event::MetaCallSponsored { who: account.clone(), sponsor: sponsor.clone() }.into(),
+		);
+		System::assert_has_event(
+			frame_system::Event::Remarked {
+				sender: account.clone(),
+				hash: sp_core::H256(sp_io::hashing::blake2_256(b"Hello")),
+			}
+			.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+
+		// Can't be replayed
+		assert_noop!(
+			EvmAccountMapping::sponsored_meta_call(
+				RuntimeOrigin::signed(sponsor.clone()),
+				account.clone(),
+				Box::new(call.clone()),
+				0,
+				5,
+				signature,
+				false
+			),
+			Error::<Test>::NonceError
+		);
+
+		// Can't be included after `valid_until`
+		run_to_block(6);
+		let message_hash =
+			EvmAccountMapping::eip712_sponsored_message_hash(account.clone(), &call.encode(), 1, 5, None);
+		assert_noop!(
+			EvmAccountMapping::sponsored_meta_call(
+				RuntimeOrigin::signed(sponsor),
+				account,
+				Box::new(call),
+				1,
+				5,
+				sign(&pair, &message_hash),
+				false
+			),
+			Error::<Test>::MetaCallExpired
+		);
+	});
+}
+
+#[test]
+fn sponsored_meta_call_can_be_bound_to_the_sponsor() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		let sponsor = AccountId::from([3u8; 32]);
+		let other_sponsor = AccountId::from([4u8; 32]);
+		let call = remark_call();
+		let message_hash = EvmAccountMapping::eip712_sponsored_message_hash(
+			account.clone(),
+			&call.encode(),
+			0,
+			0,
+			Some(&sponsor),
+		);
+		let signature = sign(&pair, &message_hash);
+
+		assert_noop!(
+			EvmAccountMapping::sponsored_meta_call(
+				RuntimeOrigin::signed(other_sponsor),
+				account.clone(),
+				Box::new(call.clone()),
+				0,
+				0,
+				signature,
+				true
+			),
+			Error::<Test>::InvalidSignature
+		);
+		// The user signed for a specific sponsor, so it isn't valid for any sponsor
+		assert_noop!(
+			EvmAccountMapping::sponsored_meta_call(
+				RuntimeOrigin::signed(sponsor.clone()),
+				account.clone(),
+				Box::new(call.clone()),
+				0,
+				0,
+				signature,
+				false
+			),
+			Error::<Test>::InvalidSignature
+		);
+		assert_ok!(EvmAccountMapping::sponsored_meta_call(
+			RuntimeOrigin::signed(sponsor),
+			account,
+			Box::new(call),
+			0,
+			0,
+			signature,
+			true
+		));
+	});
+}
+
+fn terms(nonce_key: NonceKey) -> MetaCallTerms<Balance, u64> {
+	MetaCallTerms {
+		tip: 0,
+		max_fee: DOLLARS / 2,
+		max_relayer_fee: 0,
+		valid_after: 0,
+		valid_until: 0,
+		paymaster: None,
+		nonce_key,
+	}
+}
+
+fn paymaster_terms(sponsor: &AccountId) -> MetaCallTerms<Balance, u64> {
+	MetaCallTerms { paymaster: Some(sponsor.clone()), ..terms(0) }
+}
+
+#[test]
+fn paymaster_pays_the_fees() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		// The user has no balance
+		let (pair, account) = test_pair();
+		let sponsor = AccountId::from([3u8; 32]);
+		set_balance(sponsor.clone(), DOLLARS);
+		assert_ok!(EvmAccountMapping::register_paymaster(
+			RuntimeOrigin::signed(sponsor.clone()),
+			DOLLARS / 2,
+			vec![crate::CallPolicy {
+				pallet_name: b"System".to_vec().try_into().expect("Bounded"),
+				call_name: None,
+			}]
+			.try_into()
+			.expect("Bounded"),
+			Some(DOLLARS / 4),
+			None,
+		));
+		// The budget and the deposit are reserved
+		assert_eq!(Balances::reserved_balance(&sponsor), DOLLARS / 2 + CENTS);
+
+		let call = remark_call();
+		let terms = paymaster_terms(&sponsor);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let typed_data = crate::typed_data::build(
+			&EvmAccountMapping::typed_data_context(),
+			&account,
+			&call.encode(),
+			0,
+			Some((&terms, &EvmAccountMapping::describe_call(&call.encode()).expect("Decodable"))),
+		);
+		assert_eq!(typed_data["message"]["paymaster"], sponsor.to_ss58check());
+		assert_eq!(hash_typed_data_json(&typed_data), message_hash);
+		let signature = sign(&pair, &message_hash);
+
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			terms: terms.clone(),
+		};
+		assert_ok!(EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call));
+		assert_ok!(EvmAccountMapping::meta_call_v2(
+			RuntimeOrigin::none(),
+			account.clone(),
+			Box::new(call),
+			0,
+			signature,
+			terms
+		));
+		System::assert_has_event(
+			Event::MetaCallSponsored { who: account.clone(), sponsor: sponsor.clone() }.into(),
+		);
+
+		let spent =
+			DOLLARS - Balances::free_balance(&sponsor) - Balances::reserved_balance(&sponsor);
+		assert!(spent > 0);
+		assert_eq!(crate::PaymasterSpent::<Test>::get(&sponsor, &account), spent);
+		let paymaster = crate::Paymasters::<Test>::get(&sponsor).expect("Registered");
+		assert_eq!(paymaster.budget, DOLLARS / 2 - spent);
+		// The user is tracked for a deposit
+		assert_eq!(paymaster.users, 1);
+		assert_eq!(paymaster.deposit, CENTS + MILLI_CENTS);
+		assert_eq!(Balances::reserved_balance(&sponsor), paymaster.budget + paymaster.deposit);
+		assert_eq!(Balances::free_balance(&account), 0);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+
+		// Unregistering removes the users and releases the rest
+		assert_ok!(EvmAccountMapping::unregister_paymaster(RuntimeOrigin::signed(
+			sponsor.clone()
+		)));
+		assert!(!crate::PaymasterSpent::<Test>::contains_key(&sponsor, &account));
+		assert_eq!(Balances::reserved_balance(&sponsor), 0);
+		assert_eq!(Balances::free_balance(&sponsor), DOLLARS - spent);
+	});
+}
+
+#[test]
+fn paymaster_enforces_its_policy() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		let sponsor = AccountId::from([3u8; 32]);
+		set_balance(sponsor.clone(), DOLLARS);
+		let call = remark_call();
+		let terms = paymaster_terms(&sponsor);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+		let unsigned_call = crate::Call::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(call.clone()),
+			nonce: 0,
+			signature,
+			terms: terms.clone(),
+		};
+		let register = |allowed_calls: Vec<crate::CallPolicy>,
+		                user_quota: Option<Balance>,
+		                expires_at: Option<u64>| {
+			assert_ok!(EvmAccountMapping::register_paymaster(
+				RuntimeOrigin::signed(sponsor.clone()),
+				DOLLARS / 2,
+				allowed_calls.try_into().expect("Bounded"),
+				user_quota,
+				expires_at,
+			));
+		};
+		let assert_rejected = |error: Error<Test>, validity_error: crate::MetaCallValidityError| {
+			assert_eq!(
+				EvmAccountMapping::validate_unsigned(TransactionSource::External, &unsigned_call),
+				Err(validity_error.into())
+			);
+			assert_noop!(
+				EvmAccountMapping::meta_call_v2(
+					RuntimeOrigin::none(),
+					account.clone(),
+					Box::new(call.clone()),
+					0,
+					signature,
+					terms.clone()
+				),
+				error
+			);
+		};
+
+		assert_rejected(Error::<Test>::PaymasterNotFound, crate::MetaCallValidityError::PaymasterNotFound);
+
+		// Only balance transfers are sponsored
+		register(
+			vec![crate::CallPolicy {
+				pallet_name: b"Balances".to_vec().try_into().expect("Bounded"),
+				call_name: None,
+			}],
+			None,
+			None,
+		);
+		assert_rejected(Error::<Test>::CallNotSponsored, crate::MetaCallValidityError::CallNotSponsored);
+
+		register(
+			vec![crate::CallPolicy {
+				pallet_name: b"System".to_vec().try_into().expect("Bounded"),
+				call_name: Some(b"remark_with_event".to_vec().try_into().expect("Bounded")),
+			}],
+			Some(1),
+			None,
+		);
+		assert_rejected(Error::<Test>::PaymasterQuotaExceeded, crate::MetaCallValidityError::PaymasterQuotaExceeded);
+
+		register(vec![], None, Some(1));
+		run_to_block(2);
+		assert_rejected(Error::<Test>::PaymasterExpired, crate::MetaCallValidityError::PaymasterExpired);
+
+		assert_ok!(EvmAccountMapping::unregister_paymaster(RuntimeOrigin::signed(
+			sponsor.clone()
+		)));
+		System::assert_last_event(Event::PaymasterUnregistered { sponsor: sponsor.clone() }.into());
+		assert_rejected(Error::<Test>::PaymasterNotFound, crate::MetaCallValidityError::PaymasterNotFound);
+		assert_noop!(
+			EvmAccountMapping::unregister_paymaster(RuntimeOrigin::signed(sponsor)),
+			Error::<Test>::PaymasterNotFound
+		);
+	});
+}
+
+#[test]
+fn free_quota_covers_fresh_accounts() {
+	new_test_ext().execute_with(|| {
+		FreeCallsPerPeriod::set(2);
+		run_to_block(1);
+
+		// The user has no balance
+		let (pair, account) = test_pair();
+		let meta_call = |call: RuntimeCall, nonce: u64| {
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), nonce);
+			crate::Call::<Test>::meta_call {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce,
+				signature: sign(&pair, &message_hash),
+				tip: None,
+			}
+		};
+		let dispatch = |unsigned_call: crate::Call<Test>| {
+			assert_ok!(EvmAccountMapping::validate_unsigned(
+				TransactionSource::External,
+				&unsigned_call
+			));
+			let crate::Call::meta_call { who, call, nonce, signature, tip } = unsigned_call else {
+				unreachable!()
+			};
+			EvmAccountMapping::meta_call(RuntimeOrigin::none(), who, call, nonce, signature, tip)
+		};
+		assert_eq!(EvmAccountMapping::free_calls_left(&account), 2);
+
+		// Only the calls allowed by `FreeCallFilter` are free
+		let transfer = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: AccountId::from([3u8; 32]),
+			value: 0,
+		});
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(transfer, 0)),
+			Err(crate::MetaCallValidityError::InsufficientBalanceForFees.into())
+		);
+
+		assert_ok!(dispatch(meta_call(remark_call(), 0)));
+		System::assert_has_event(Event::FreeCallUsed { who: account.clone(), remaining: 1 }.into());
+		assert_ok!(dispatch(meta_call(remark_call(), 1)));
+		System::assert_has_event(Event::FreeCallUsed { who: account.clone(), remaining: 0 }.into());
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 2);
+		assert_eq!(Balances::free_balance(&account), 0);
+
+		// The quota is used up
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(remark_call(), 2)),
+			Err(crate::MetaCallValidityError::InsufficientBalanceForFees.into())
+		);
+
+		// The quota regenerates in the next period, the past period is removed
+		run_to_block(10);
+		assert!(!crate::FreeCallsUsed::<Test>::contains_key(0, &account));
+		assert_eq!(EvmAccountMapping::free_calls_left(&account), 2);
+		assert_ok!(dispatch(meta_call(remark_call(), 2)));
+		System::assert_has_event(Event::FreeCallUsed { who: account.clone(), remaining: 1 }.into());
+
+		// Funded accounts pay as usual
+		set_balance(account.clone(), DOLLARS);
+		assert_eq!(EvmAccountMapping::free_calls_left(&account), 0);
+		assert_ok!(dispatch(meta_call(remark_call(), 3)));
+		assert!(Balances::free_balance(&account) < DOLLARS);
+	});
+}
+
+#[test]
+fn free_calls_of_all_accounts_are_capped() {
+	new_test_ext().execute_with(|| {
+		FreeCallsPerPeriod::set(2);
+		MaxFreeCallsPerPeriod::set(1);
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		let other_account = AccountId::from([4u8; 32]);
+		assert_eq!(EvmAccountMapping::free_calls_left(&other_account), 2);
+
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash(account.clone(), &remark_call().encode(), 0);
+		assert_ok!(EvmAccountMapping::meta_call(
+			RuntimeOrigin::none(),
+			account.clone(),
+			Box::new(remark_call()),
+			0,
+			sign(&pair, &message_hash),
+			None
+		));
+		System::assert_has_event(Event::FreeCallUsed { who: account.clone(), remaining: 1 }.into());
+
+		// No account has free meta-calls left in the period
+		assert_eq!(EvmAccountMapping::free_calls_left(&account), 0);
+		assert_eq!(EvmAccountMapping::free_calls_left(&other_account), 0);
+
+		run_to_block(10);
+		assert_eq!(EvmAccountMapping::free_calls_left(&other_account), 2);
+		MaxFreeCallsPerPeriod::set(u32::MAX);
+	});
+}
+
+fn bundle_item(pair: &ecdsa::Pair, who: &AccountId, nonce: u64) -> crate::MetaCallItemOf<Test> {
+	let call = remark_call();
+	let terms = MetaCallTerms { max_relayer_fee: 300, ..terms(0) };
+	let message_hash =
+		EvmAccountMapping::eip712_message_hash_v2(who.clone(), &call.encode(), nonce, &terms);
+	crate::MetaCallItem {
+		who: who.clone(),
+		call,
+		nonce,
+		signature: sign(pair, &message_hash),
+		terms,
+	}
+}
+
+#[test]
+fn meta_call_bundle_reports_every_item() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		set_balance(relayer.clone(), DOLLARS);
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let other_pair = ecdsa::Pair::from_seed(&[2u8; 32]);
+		let other_account = crate::SubstrateAddressConverter::try_convert(
+			&other_pair.public().to_raw_vec(),
+		)
+		.expect("Convertable");
+		set_balance(other_account.clone(), DOLLARS);
+
+		let items = vec![
+			bundle_item(&pair, &account, 0),
+			// Signed by another key
+			bundle_item(&other_pair, &account, 1),
+			// Replays the first item
+			bundle_item(&pair, &account, 0),
+			bundle_item(&other_pair, &other_account, 0),
+		];
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer.clone()),
+			items,
+			false
+		));
+		for (index, who, result) in [
+			(0, &account, Ok(())),
+			(1, &account, Err(Error::<Test>::InvalidSignature.into())),
+			(2, &account, Err(Error::<Test>::NonceError.into())),
+			(3, &other_account, Ok(())),
+		] {
+			System::assert_has_event(
+				Event::BundleItemDone { who: who.clone(), index, result }.into(),
+			);
+		}
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+		assert_eq!(EvmAccountMapping::account_nonce(&other_account), 1);
+
+		// The users paid their own fees, the relayer took its share of the service fees of the
+		// included items, capped by the signed `maxRelayerFee`
+		assert!(Balances::free_balance(&account) < DOLLARS - 1000);
+		assert!(Balances::free_balance(&other_account) < DOLLARS - 1000);
+		assert_eq!(Balances::free_balance(&relayer), DOLLARS + 2 * 300);
+	});
+}
+
+#[test]
+fn meta_call_bundle_items_are_bound_to_the_chain() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		set_balance(relayer.clone(), DOLLARS);
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let item = bundle_item(&pair, &account, 0);
+
+		// The item signed for another chain can't be replayed here
+		let genesis_hash = frame_system::BlockHash::<Test>::get(0);
+		frame_system::BlockHash::<Test>::insert(0, sp_core::H256::repeat_byte(1));
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer.clone()),
+			vec![item.clone()],
+			false
+		));
+		System::assert_last_event(
+			Event::BundleItemDone {
+				who: account.clone(),
+				index: 0,
+				result: Err(Error::<Test>::InvalidSignature.into()),
+			}
+			.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+
+		// Nor after the validity window signed by the user
+		frame_system::BlockHash::<Test>::insert(0, genesis_hash);
+		let crate::MetaCallItem { call, terms, .. } = item;
+		let terms = MetaCallTerms { valid_until: 1, ..terms };
+		run_to_block(2);
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let item = crate::MetaCallItem {
+			who: account.clone(),
+			call,
+			nonce: 0,
+			signature: sign(&pair, &message_hash),
+			terms,
+		};
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer),
+			vec![item],
+			true
+		));
+		System::assert_last_event(
+			Event::BundleItemDone {
+				who: account.clone(),
+				index: 0,
+				result: Err(Error::<Test>::MetaCallExpired.into()),
+			}
+			.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+	});
+}
+
+#[test]
+fn relayed_meta_call_pays_the_relayer() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		set_balance(relayer.clone(), DOLLARS);
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let call = remark_call();
+		let terms = MetaCallTerms { max_relayer_fee: 300, ..terms(0) };
+		let message_hash =
+			EvmAccountMapping::eip712_message_hash_v2(account.clone(), &call.encode(), 0, &terms);
+		let signature = sign(&pair, &message_hash);
+
+		// The relayer can't raise the cap
+		assert_noop!(
+			EvmAccountMapping::relayed_meta_call(
+				RuntimeOrigin::signed(relayer.clone()),
+				account.clone(),
+				Box::new(call.clone()),
+				0,
+				signature,
+				MetaCallTerms { max_relayer_fee: 1000, ..terms.clone() }
+			),
+			Error::<Test>::InvalidSignature
+		);
+
+		// Half of the service fee is 500, the user capped it to 300
+		assert_ok!(EvmAccountMapping::relayed_meta_call(
+			RuntimeOrigin::signed(relayer.clone()),
+			account.clone(),
+			Box::new(call),
+			0,
+			signature,
+			terms
+		));
+		System::assert_has_event(
+			Event::RelayerFeePaid { relayer: relayer.clone(), who: account.clone(), amount: 300 }
+				.into(),
+		);
+		assert_eq!(Balances::free_balance(&relayer), DOLLARS + 300);
+		assert!(Balances::free_balance(&account) < DOLLARS - 1000);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+	});
+}
+
+#[test]
+fn meta_call_bundle_can_be_paid_by_the_relayer() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		// The user has no balance
+		let relayer = AccountId::from([3u8; 32]);
+		let (pair, account) = test_pair();
+
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer.clone()),
+			vec![bundle_item(&pair, &account, 0)],
+			true
+		));
+		System::assert_has_event(
+			Event::MetaCallSponsored { who: account.clone(), sponsor: relayer }.into(),
+		);
+		System::assert_has_event(
+			frame_system::Event::Remarked {
+				sender: account.clone(),
+				hash: sp_core::H256(sp_io::hashing::blake2_256(b"Hello")),
+			}
+			.into(),
+		);
+		System::assert_last_event(
+			Event::BundleItemDone { who: account.clone(), index: 0, result: Ok(()) }.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 1);
+	});
+}
+
+#[test]
+fn meta_call_bundle_rejects_empty_or_oversized_bundles() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		let (pair, account) = test_pair();
+		assert_noop!(
+			EvmAccountMapping::meta_call_bundle(RuntimeOrigin::signed(relayer.clone()), vec![], true),
+			Error::<Test>::EmptyBundle
+		);
+		let max_items = <Test as crate::Config>::MaxBundleItems::get() as u64;
+		let items = (0..=max_items).map(|nonce| bundle_item(&pair, &account, nonce)).collect();
+		assert_noop!(
+			EvmAccountMapping::meta_call_bundle(RuntimeOrigin::signed(relayer), items, true),
+			Error::<Test>::TooManyBundleItems
+		);
+	});
+}
+
+#[test]
+fn admin_can_pause_meta_calls() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let validate = |nonce: u64| {
+			EvmAccountMapping::validate_unsigned(
+				TransactionSource::External,
+				&remark_meta_call(&pair, &account, nonce),
+			)
+		};
+
+		assert_noop!(
+			EvmAccountMapping::pause(RuntimeOrigin::signed(account.clone()), None),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			EvmAccountMapping::pause(RuntimeOrigin::root(), Some(1)),
+			Error::<Test>::InvalidUnpauseBlock
+		);
+		assert_ok!(EvmAccountMapping::pause(RuntimeOrigin::root(), Some(5)));
+		System::assert_last_event(Event::MetaCallsPaused { unpause_at: Some(5) }.into());
+		assert_eq!(validate(0), Err(crate::MetaCallValidityError::Paused.into()));
+
+		// The signed submissions are paused too
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer),
+			vec![bundle_item(&pair, &account, 0)],
+			true
+		));
+		System::assert_last_event(
+			Event::BundleItemDone {
+				who: account.clone(),
+				index: 0,
+				result: Err(Error::<Test>::MetaCallsPaused.into()),
+			}
+			.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+
+		// Unpaused automatically
+		run_to_block(5);
+		System::assert_has_event(Event::MetaCallsUnpaused.into());
+		assert!(!crate::Paused::<Test>::get());
+		assert_eq!(crate::UnpauseAt::<Test>::get(), None);
+		assert_ok!(validate(0));
+
+		// Until unpaused by the admin
+		assert_ok!(EvmAccountMapping::pause(RuntimeOrigin::root(), None));
+		run_to_block(100);
+		assert_eq!(validate(0), Err(crate::MetaCallValidityError::Paused.into()));
+		assert_ok!(EvmAccountMapping::unpause(RuntimeOrigin::root()));
+		System::assert_last_event(Event::MetaCallsUnpaused.into());
+		assert_ok!(validate(0));
+		assert_noop!(EvmAccountMapping::unpause(RuntimeOrigin::root()), Error::<Test>::NotPaused);
+	});
+}
+
+#[test]
+fn admin_can_freeze_accounts() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		let meta_call = |nonce: u64| remark_meta_call(&pair, &account, nonce);
+
+		assert_noop!(
+			EvmAccountMapping::freeze_account(
+				RuntimeOrigin::signed(account.clone()),
+				account.clone()
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(EvmAccountMapping::freeze_account(RuntimeOrigin::root(), account.clone()));
+		System::assert_last_event(Event::AccountFrozen { who: account.clone() }.into());
+		assert_eq!(
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, &meta_call(0)),
+			Err(crate::MetaCallValidityError::AccountFrozen.into())
+		);
+		assert_eq!(
+			apply_unsigned(meta_call(0)),
+			Err(crate::MetaCallValidityError::AccountFrozen.into())
+		);
+
+		assert_ok!(EvmAccountMapping::thaw_account(RuntimeOrigin::root(), account.clone()));
+		System::assert_last_event(Event::AccountThawed { who: account.clone() }.into());
+		assert_ok!(apply_unsigned(meta_call(0)).expect("Included"));
+		assert_noop!(
+			EvmAccountMapping::thaw_account(RuntimeOrigin::root(), account),
+			Error::<Test>::AccountNotFrozen
+		);
+	});
+}
+
+#[test]
+fn call_filter_cant_be_bypassed() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+
+		let relayer = AccountId::from([3u8; 32]);
+		set_balance(relayer.clone(), DOLLARS);
+		let (pair, account) = test_pair();
+		set_balance(account.clone(), DOLLARS);
+		// The first meta-call of an account can only be a balance call
+		BalancesOnlyBelowNonce::set(1);
+
+		let meta_call = |call: RuntimeCall, nonce: u64| {
+			let message_hash =
+				EvmAccountMapping::eip712_message_hash(account.clone(), &call.encode(), nonce);
+			crate::Call::<Test>::meta_call {
+				who: account.clone(),
+				call: Box::new(call),
+				nonce,
+				signature: sign(&pair, &message_hash),
+				tip: None,
+			}
+		};
+		let validate = |unsigned_call: &crate::Call<Test>| {
+			EvmAccountMapping::validate_unsigned(TransactionSource::External, unsigned_call)
+		};
+		let transfer = RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive {
+			dest: relayer.clone(),
+			value: CENTS,
+		});
+
+		// A forbidden meta-call doesn't enter the pool
+		assert_eq!(
+			validate(&meta_call(remark_call(), 0)),
+			Err(crate::MetaCallValidityError::CallFiltered.into())
+		);
+		assert_ok!(validate(&meta_call(transfer.clone(), 0)));
+
+		// The signed submissions are filtered when dispatching, the user isn't charged
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer.clone()),
+			vec![bundle_item(&pair, &account, 0)],
+			false
+		));
+		System::assert_last_event(
+			Event::BundleItemDone {
+				who: account.clone(),
+				index: 0,
+				result: Err(frame_system::Error::<Test>::CallFiltered.into()),
+			}
+			.into(),
+		);
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+		assert_eq!(Balances::free_balance(&account), DOLLARS);
+
+		// The filter is checked before the nonce is bumped when the relayer pays
+		assert_ok!(EvmAccountMapping::meta_call_bundle(
+			RuntimeOrigin::signed(relayer.clone()),
+			vec![bundle_item(&pair, &account, 0)],
+			true
+		));
+		System::assert_last_event(
+			Event::BundleItemDone {
+				who: account.clone(),
+				index: 0,
+				result: Err(frame_system::Error::<Test>::CallFiltered.into()),
+			}
+			.into(),
+		);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::System(frame_system::Event::Remarked { .. })
+		)));
+		assert_eq!(EvmAccountMapping::account_nonce(&account), 0);
+
+		// And when a sponsor pays
+		let message_hash = EvmAccountMapping::eip712_sponsored_message_hash(
+			account.clone(),
+			&remark_call().encode(),
+			0,
+			0,
+			None,
+		);
+		assert_noop!(
+			EvmAccountMapping::sponsored_meta_call(
+				RuntimeOrigin::signed(relayer),
+				account.clone(),
+				Box::new(remark_call()),
+				0,
+				0,
+				sign(&pair, &message_hash),
+				false
+			),
+			frame_system::Error::<Test>::CallFiltered
+		);
+
+		// The restricted nonces can't be skipped by cancelling them
+		assert_noop!(
+			EvmAccountMapping::cancel_meta_nonce(RuntimeOrigin::signed(account.clone()), 0, 0),
+			frame_system::Error::<Test>::CallFiltered
+		);
+		let message_hash = EvmAccountMapping::eip712_cancel_message_hash(account.clone(), 0, 0, 0);
+		let unsigned_call = crate::Call::<Test>::meta_cancel_nonce {
+			who: account.clone(),
+			nonce_key: 0,
+			up_to: 0,
+			valid_until: 0,
+			signature: sign(&pair, &message_hash),
+		};
+		assert_eq!(validate(&unsigned_call), Err(crate::MetaCallValidityError::CallFiltered.into()));
+		assert_noop!(
+			EvmAccountMapping::meta_cancel_nonce(
+				RuntimeOrigin::none(),
+				account.clone(),
+				0,
+				0,
+				0,
+				sign(&pair, &message_hash)
+			),
+			frame_system::Error::<Test>::CallFiltered
+		);
+
+		// Nor by using another lane, which the filter sees
+		let terms = terms(1);
+		let message_hash = EvmAccountMapping::eip712_message_hash_v2(
+			account.clone(),
+			&remark_call().encode(),
+			1,
+			&terms,
+		);
+		let unsigned_call = crate::Call::<Test>::meta_call_v2 {
+			who: account.clone(),
+			call: Box::new(remark_call()),
+			nonce: 1,
+			signature: sign(&pair, &message_hash),
+			terms,
+		};
+		assert_eq!(validate(&unsigned_call), Err(crate::MetaCallValidityError::CallFiltered.into()));
+
+		// The later meta-calls can make any call
+		assert_ok!(EvmAccountMapping::meta_call(
+			RuntimeOrigin::none(),
+			account.clone(),
+			Box::new(transfer),
+			0,
+			[0u8; 65],
+			None
+		));
+		assert_ok!(validate(&meta_call(remark_call(), 1)));
+		BalancesOnlyBelowNonce::set(0);
+	});
+}
+
 #[test]
 fn evm_transparent_converter_works() {
 	// Compressed 029df1e69b8b7c2da2efe0069dc141c2cec0317bf3fd135abaeb69ee33801f5970
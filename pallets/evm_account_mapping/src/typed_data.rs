@@ -0,0 +1,314 @@
+// This file is part of EVM Account Mapping Pallet.
+
+// Copyright (C) HashForest Technology Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Builds the JSON typed data the wallet signs with `eth_signTypedData_v4`.
+//!
+//! The fields are derived from the same type strings the pallet hashes, so the JSON can't drift
+//! from what `Pallet::eip712_message_hash` and `Pallet::eip712_message_hash_v2` verify.
+
+use serde_json::{json, Map, Value};
+use sp_core::{
+	crypto::{AccountId32, Ss58Codec},
+	U256,
+};
+use sp_runtime::traits::UniqueSaturatedInto;
+
+use crate::{
+	template_encoded_type, CallDescription, MetaCallTerms, Nonce, NonceKey, TemplateMembers,
+	TemplateValue, TypedDataContext, CANCEL_META_NONCE_TYPE, SPONSORED_SUBSTRATE_CALL_TYPE, SUBSTRATE_BATCH_TYPE,
+	SUBSTRATE_CALL_TYPE_V1, SUBSTRATE_CALL_TYPE_V2,
+};
+
+/// The type of the EIP-712 domain without salt.
+pub const EIP712_DOMAIN_TYPE: &str =
+	"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// The type of the EIP-712 domain with salt.
+pub const EIP712_DOMAIN_TYPE_WITH_SALT: &str =
+	"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract,bytes32 salt)";
+
+/// Splits an encoded EIP-712 type like `Name(type1 name1,type2 name2)` into the primary type
+/// name and its `(type, name)` members.
+pub fn parse_type(encoded_type: &str) -> (&str, Vec<(&str, &str)>) {
+	let (primary_type, members) = encoded_type
+		.trim_end_matches(')')
+		.split_once('(')
+		.expect("EIP-712 type must be `Name(members)`");
+	let members = members
+		.split(',')
+		.filter(|member| !member.is_empty())
+		.map(|member| member.split_once(' ').expect("EIP-712 member must be `type name`"))
+		.collect();
+	(primary_type, members)
+}
+
+/// Builds the `types`, `domain`, `primaryType` and `message` of a meta-call, it is the v2 typed
+/// data if `terms` and the description of the call are set.
+pub fn build<Balance, BlockNumber>(
+	context: &TypedDataContext,
+	who: &AccountId32,
+	call_data: &[u8],
+	nonce: Nonce,
+	terms: Option<(&MetaCallTerms<Balance, BlockNumber>, &CallDescription)>,
+) -> Value
+where
+	Balance: Copy + UniqueSaturatedInto<u128>,
+	BlockNumber: Copy + UniqueSaturatedInto<u64>,
+{
+	let mut message_values = Map::new();
+	message_values.insert(
+		"who".into(),
+		json!(who.to_ss58check_with_version(context.ss58_prefix.into())),
+	);
+	message_values.insert("callData".into(), hex_value(call_data));
+	message_values.insert("nonce".into(), uint64_value(nonce));
+	let call_type = match terms {
+		Some((terms, description)) => {
+			message_values.insert("palletName".into(), string_value(&description.pallet_name));
+			message_values.insert("callName".into(), string_value(&description.call_name));
+			message_values.insert("callArgs".into(), string_value(&description.call_args));
+			let tip: u128 = terms.tip.unique_saturated_into();
+			let max_fee: u128 = terms.max_fee.unique_saturated_into();
+			let max_relayer_fee: u128 = terms.max_relayer_fee.unique_saturated_into();
+			let valid_after: u64 = terms.valid_after.unique_saturated_into();
+			let valid_until: u64 = terms.valid_until.unique_saturated_into();
+			message_values.insert("nonceKey".into(), uint64_value(terms.nonce_key));
+			message_values.insert("tip".into(), uint256_value(tip.into()));
+			message_values.insert("maxFee".into(), uint256_value(max_fee.into()));
+			message_values.insert("maxRelayerFee".into(), uint256_value(max_relayer_fee.into()));
+			message_values.insert("validAfter".into(), uint64_value(valid_after));
+			message_values.insert("validUntil".into(), uint64_value(valid_until));
+			message_values.insert("genesisHash".into(), hex_value(&context.genesis_hash));
+			message_values.insert("specVersion".into(), json!(context.spec_version));
+			message_values.insert("txVersion".into(), json!(context.transaction_version));
+			message_values.insert(
+				"paymaster".into(),
+				json!(terms
+					.paymaster
+					.as_ref()
+					.map(|sponsor| sponsor.to_ss58check_with_version(context.ss58_prefix.into()))
+					.unwrap_or_default()),
+			);
+			SUBSTRATE_CALL_TYPE_V2
+		},
+		None => SUBSTRATE_CALL_TYPE_V1,
+	};
+
+	typed_data(context, call_type, &message_values)
+}
+
+/// Builds the `SubstrateBatch` typed data of a `meta_batch_call`, `calls` are the encoded calls.
+pub fn build_batch(
+	context: &TypedDataContext,
+	who: &AccountId32,
+	calls: &[Vec<u8>],
+	nonce: Nonce,
+	atomic: bool,
+	max_fee: u128,
+	valid_until: u64,
+) -> Value {
+	let mut message_values = Map::new();
+	message_values.insert(
+		"who".into(),
+		json!(who.to_ss58check_with_version(context.ss58_prefix.into())),
+	);
+	message_values.insert("calls".into(), Value::Array(calls.iter().map(hex_value).collect()));
+	message_values.insert("nonce".into(), uint64_value(nonce));
+	message_values.insert("atomic".into(), json!(atomic));
+	message_values.insert("maxFee".into(), uint256_value(max_fee.into()));
+	message_values.insert("validUntil".into(), uint64_value(valid_until));
+	message_values.insert("genesisHash".into(), hex_value(&context.genesis_hash));
+	message_values.insert("specVersion".into(), json!(context.spec_version));
+	message_values.insert("txVersion".into(), json!(context.transaction_version));
+	typed_data(context, SUBSTRATE_BATCH_TYPE, &message_values)
+}
+
+/// Builds the `SponsoredSubstrateCall` typed data of a `sponsored_meta_call`, `sponsor` is `None`
+/// if any sponsor may submit it.
+pub fn build_sponsored(
+	context: &TypedDataContext,
+	who: &AccountId32,
+	call_data: &[u8],
+	description: &CallDescription,
+	nonce: Nonce,
+	valid_until: u64,
+	sponsor: Option<&AccountId32>,
+) -> Value {
+	let ss58_prefix = context.ss58_prefix.into();
+	let mut message_values = Map::new();
+	message_values.insert("who".into(), json!(who.to_ss58check_with_version(ss58_prefix)));
+	message_values.insert("palletName".into(), string_value(&description.pallet_name));
+	message_values.insert("callName".into(), string_value(&description.call_name));
+	message_values.insert("callArgs".into(), string_value(&description.call_args));
+	message_values.insert("callData".into(), hex_value(call_data));
+	message_values.insert("nonce".into(), uint64_value(nonce));
+	message_values.insert("validUntil".into(), uint64_value(valid_until));
+	message_values.insert("genesisHash".into(), hex_value(&context.genesis_hash));
+	message_values.insert("specVersion".into(), json!(context.spec_version));
+	message_values.insert("txVersion".into(), json!(context.transaction_version));
+	message_values.insert(
+		"sponsor".into(),
+		json!(sponsor
+			.map(|sponsor| sponsor.to_ss58check_with_version(ss58_prefix))
+			.unwrap_or_default()),
+	);
+	typed_data(context, SPONSORED_SUBSTRATE_CALL_TYPE, &message_values)
+}
+
+/// Builds the `CancelMetaNonce` typed data of a `meta_cancel_nonce`.
+pub fn build_cancel(
+	context: &TypedDataContext,
+	who: &AccountId32,
+	nonce_key: NonceKey,
+	up_to: Nonce,
+	valid_until: u64,
+) -> Value {
+	let mut message_values = Map::new();
+	message_values.insert(
+		"who".into(),
+		json!(who.to_ss58check_with_version(context.ss58_prefix.into())),
+	);
+	message_values.insert("nonceKey".into(), uint64_value(nonce_key));
+	message_values.insert("upTo".into(), uint64_value(up_to));
+	message_values.insert("validUntil".into(), uint64_value(valid_until));
+	message_values.insert("genesisHash".into(), hex_value(&context.genesis_hash));
+	message_values.insert("specVersion".into(), json!(context.spec_version));
+	message_values.insert("txVersion".into(), json!(context.transaction_version));
+	typed_data(context, CANCEL_META_NONCE_TYPE, &message_values)
+}
+
+/// Builds the typed data of a call template, see `CallTemplates`.
+pub fn build_template(
+	context: &TypedDataContext,
+	primary_type: &str,
+	members: &[(&str, TemplateValue)],
+	nonce: Nonce,
+	valid_until: u64,
+) -> Value {
+	let mut message_values = Map::new();
+	for (name, value) in members {
+		let value = match value {
+			TemplateValue::String(value) => string_value(value),
+			TemplateValue::Uint(value) => uint256_value(*value),
+			TemplateValue::Bytes(value) => hex_value(value),
+		};
+		message_values.insert((*name).into(), value);
+	}
+	message_values.insert("nonce".into(), uint64_value(nonce));
+	message_values.insert("validUntil".into(), uint64_value(valid_until));
+	message_values.insert("genesisHash".into(), hex_value(&context.genesis_hash));
+	message_values.insert("specVersion".into(), json!(context.spec_version));
+	message_values.insert("txVersion".into(), json!(context.transaction_version));
+	typed_data(context, &template_encoded_type(primary_type, members), &message_values)
+}
+
+/// Like `build_template`, with the `TemplateMembers` the runtime API returns for an encoded
+/// template.
+pub fn build_template_members(
+	context: &TypedDataContext,
+	template: &TemplateMembers,
+	nonce: Nonce,
+	valid_until: u64,
+) -> Value {
+	let primary_type = String::from_utf8_lossy(&template.primary_type);
+	let names: Vec<_> =
+		template.members.iter().map(|(name, _)| String::from_utf8_lossy(name)).collect();
+	let members: Vec<_> = names
+		.iter()
+		.zip(&template.members)
+		.map(|(name, (_, value))| (name.as_ref(), value.clone()))
+		.collect();
+	build_template(context, &primary_type, &members, nonce, valid_until)
+}
+
+/// Assembles the typed data of the message in the EIP-712 domain of `context`.
+fn typed_data(
+	context: &TypedDataContext,
+	message_type: &str,
+	message_values: &Map<String, Value>,
+) -> Value {
+	let mut domain_values = Map::new();
+	domain_values.insert("name".into(), string_value(&context.domain.name));
+	domain_values.insert("version".into(), string_value(&context.domain.version));
+	domain_values.insert("chainId".into(), uint256_value(context.domain.chain_id));
+	domain_values.insert("verifyingContract".into(), hex_value(context.domain.verifying_contract));
+	let domain_type = match context.salt {
+		Some(salt) => {
+			domain_values.insert("salt".into(), hex_value(salt));
+			EIP712_DOMAIN_TYPE_WITH_SALT
+		},
+		None => EIP712_DOMAIN_TYPE,
+	};
+
+	let (domain_type_name, domain_members, domain) = typed_struct(domain_type, &domain_values);
+	let (primary_type, message_members, message) = typed_struct(message_type, message_values);
+
+	json!({
+		"types": {
+			domain_type_name: domain_members,
+			primary_type: message_members,
+		},
+		"primaryType": primary_type,
+		"domain": domain,
+		"message": message,
+	})
+}
+
+/// Picks the values of the members of `encoded_type`, in the order of the type.
+fn typed_struct<'a>(
+	encoded_type: &'a str,
+	values: &Map<String, Value>,
+) -> (&'a str, Vec<Value>, Map<String, Value>) {
+	let (type_name, members) = parse_type(encoded_type);
+	let mut picked = Map::new();
+	let members = members
+		.into_iter()
+		.map(|(member_type, member_name)| {
+			let value = values
+				.get(member_name)
+				.unwrap_or_else(|| panic!("no value for `{member_name}` of `{type_name}`"));
+			picked.insert(member_name.into(), value.clone());
+			json!({ "name": member_name, "type": member_type })
+		})
+		.collect();
+	(type_name, members, picked)
+}
+
+fn string_value(bytes: &[u8]) -> Value {
+	json!(String::from_utf8_lossy(bytes))
+}
+
+fn hex_value(bytes: impl AsRef<[u8]>) -> Value {
+	json!(format!("0x{}", hex::encode(bytes)))
+}
+
+/// Wallets take `uint256` as a number, fall back to a decimal string if it exceeds the safe
+/// integer range of JavaScript.
+fn uint256_value(value: U256) -> Value {
+	if value <= U256::from((1u64 << 53) - 1) {
+		json!(value.low_u64())
+	} else {
+		json!(value.to_string())
+	}
+}
+
+/// Like `uint256_value`, for the `uint64` members, e.g. `Nonce::MAX` which closes a lane, or the
+/// nonce keys picked at random.
+fn uint64_value(value: u64) -> Value {
+	uint256_value(value.into())
+}
@@ -0,0 +1,156 @@
+// This file is part of EVM Account Mapping Pallet.
+
+// Copyright (C) HashForest Technology Pte. Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `InvalidTransaction::Custom` codes of the rejected meta-calls.
+//!
+//! Clients and RPC nodes can decode the code of a rejected meta-call with
+//! `MetaCallValidityError::try_from` and show `MetaCallValidityError::message` to the user.
+
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
+
+/// Why `validate_unsigned` rejected a meta-call, it is returned as
+/// `InvalidTransaction::Custom(code)`.
+///
+/// The codes are part of the API, new variants must only be appended.
+#[derive(Clone, Copy, PartialEq, Eq, sp_runtime::RuntimeDebug)]
+#[repr(u8)]
+pub enum MetaCallValidityError {
+	/// The call can't be submitted unsigned.
+	UnsupportedCall = 0,
+	/// The `r`, `s` or `v` of the signature is malformed.
+	BadSignatureEncoding = 1,
+	/// No public key can be recovered from the signature.
+	RecoveryFailed = 2,
+	/// The `AddressConverter` can't convert the recovered public key into an account.
+	UnsupportedPublicKeyForm = 3,
+	/// The signature is made for an EIP-712 domain which is no longer accepted.
+	DomainMismatch = 4,
+	/// The call is rejected by the call filter.
+	CallFiltered = 5,
+	/// The call template can't be converted into a call.
+	InvalidCallTemplate = 6,
+	/// The batch is empty or has more than `MaxBatchCalls` calls.
+	InvalidBatchSize = 7,
+	/// The legacy typed data doesn't cover the tip, so it must be zero.
+	UnsignedTip = 8,
+	/// The transaction fee plus the service fee exceeds the signed `maxFee`.
+	MaxFeeExceeded = 9,
+	/// The payer can't pay the transaction fee plus the service fee.
+	InsufficientBalanceForFees = 10,
+	/// The named sponsor has no paymaster.
+	PaymasterNotFound = 11,
+	/// The paymaster has expired.
+	PaymasterExpired = 12,
+	/// The paymaster doesn't pay for the call.
+	CallNotSponsored = 13,
+	/// The budget of the paymaster can't cover the fees.
+	PaymasterBudgetExhausted = 14,
+	/// The paymaster has paid the quota of the user.
+	PaymasterQuotaExceeded = 15,
+	/// The account has reached `MaxMetaCallsPerPeriod`, counting its pending meta-calls.
+	RateLimited = 16,
+	/// The meta-calls are paused.
+	Paused = 17,
+	/// The meta-calls of the account are frozen.
+	AccountFrozen = 18,
+}
+
+impl MetaCallValidityError {
+	/// All the errors, in the order of their codes.
+	pub const ALL: [Self; 19] = [
+		Self::UnsupportedCall,
+		Self::BadSignatureEncoding,
+		Self::RecoveryFailed,
+		Self::UnsupportedPublicKeyForm,
+		Self::DomainMismatch,
+		Self::CallFiltered,
+		Self::InvalidCallTemplate,
+		Self::InvalidBatchSize,
+		Self::UnsignedTip,
+		Self::MaxFeeExceeded,
+		Self::InsufficientBalanceForFees,
+		Self::PaymasterNotFound,
+		Self::PaymasterExpired,
+		Self::CallNotSponsored,
+		Self::PaymasterBudgetExhausted,
+		Self::PaymasterQuotaExceeded,
+		Self::RateLimited,
+		Self::Paused,
+		Self::AccountFrozen,
+	];
+
+	/// The code in `InvalidTransaction::Custom`.
+	pub fn code(self) -> u8 {
+		self as u8
+	}
+
+	/// A message wallets can show to the user.
+	pub fn message(self) -> &'static str {
+		match self {
+			Self::UnsupportedCall => "The call can't be submitted as a meta-call",
+			Self::BadSignatureEncoding => "The signature is malformed",
+			Self::RecoveryFailed => "The signer can't be recovered from the signature",
+			Self::UnsupportedPublicKeyForm =>
+				"The public key of the signer can't be mapped to an account",
+			Self::DomainMismatch => "The signature is made for an outdated EIP-712 domain",
+			Self::CallFiltered => "The call isn't allowed",
+			Self::InvalidCallTemplate => "The call template is invalid",
+			Self::InvalidBatchSize => "The batch is empty or has too many calls",
+			Self::UnsignedTip => "The tip must be zero, it isn't covered by the signature",
+			Self::MaxFeeExceeded => "The fees exceed the signed maximum fee",
+			Self::InsufficientBalanceForFees =>
+				"The balance can't pay the transaction fee plus the service fee",
+			Self::PaymasterNotFound => "The paymaster isn't registered",
+			Self::PaymasterExpired => "The paymaster has expired",
+			Self::CallNotSponsored => "The paymaster doesn't pay for the call",
+			Self::PaymasterBudgetExhausted => "The budget of the paymaster is exhausted",
+			Self::PaymasterQuotaExceeded => "The paymaster quota of the account is used up",
+			Self::RateLimited => "The account has made too many meta-calls, retry later",
+			Self::Paused => "The meta-calls are paused",
+			Self::AccountFrozen => "The meta-calls of the account are frozen",
+		}
+	}
+
+	/// Decodes the error of a rejected meta-call, `None` if it isn't one of these errors.
+	pub fn from_validity_error(error: &TransactionValidityError) -> Option<Self> {
+		match error {
+			TransactionValidityError::Invalid(InvalidTransaction::Custom(code)) =>
+				Self::try_from(*code).ok(),
+			_ => None,
+		}
+	}
+}
+
+impl TryFrom<u8> for MetaCallValidityError {
+	type Error = ();
+
+	fn try_from(code: u8) -> Result<Self, Self::Error> {
+		Self::ALL.get(code as usize).copied().ok_or(())
+	}
+}
+
+impl From<MetaCallValidityError> for InvalidTransaction {
+	fn from(error: MetaCallValidityError) -> Self {
+		InvalidTransaction::Custom(error.code())
+	}
+}
+
+impl From<MetaCallValidityError> for TransactionValidityError {
+	fn from(error: MetaCallValidityError) -> Self {
+		InvalidTransaction::from(error).into()
+	}
+}
@@ -145,6 +145,89 @@ impl OnUnbalanced<NegativeImbalance> for DealWithServiceFee {
 	}
 }
 
+/// Renders the arguments of the common calls, so wallets can show them in the typed data.
+pub struct RuntimeCallDescriber;
+impl pallet_evm_account_mapping::CallDescriber<RuntimeCall> for RuntimeCallDescriber {
+	fn describe(call: &RuntimeCall) -> pallet_evm_account_mapping::CallDescription {
+		use pallet_evm_account_mapping::{CallDescriber, CallMetadataDescriber};
+		use sp_core::crypto::Ss58Codec;
+
+		let mut description = CallMetadataDescriber::describe(call);
+		let call_args = match call {
+			RuntimeCall::Balances(
+				BalancesCall::transfer_allow_death { dest: sp_runtime::MultiAddress::Id(dest), value } |
+				BalancesCall::transfer_keep_alive { dest: sp_runtime::MultiAddress::Id(dest), value },
+			) => {
+				let ss58_prefix = <SS58Prefix as frame_support::traits::Get<u8>>::get();
+				alloc::format!(
+					"dest: {}, value: {}",
+					dest.to_ss58check_with_version(ss58_prefix.into()),
+					value
+				)
+			},
+			RuntimeCall::System(
+				SystemCall::remark { remark } | SystemCall::remark_with_event { remark },
+			) => match core::str::from_utf8(remark) {
+				Ok(remark) => alloc::format!("remark: {}", remark),
+				Err(_) => return description,
+			},
+			_ => return description,
+		};
+		description.call_args = call_args.into_bytes();
+		description
+	}
+}
+
+/// The calls which can be signed as dedicated EIP-712 primary types.
+#[derive(Clone, PartialEq, Eq, codec::Encode, codec::Decode, sp_runtime::RuntimeDebug, scale_info::TypeInfo)]
+pub enum RuntimeCallTemplate {
+	/// `Transfer(string to,uint256 amount,uint64 nonce)`, a `Balances::transfer_keep_alive`.
+	Transfer { to: AccountId, amount: Balance },
+	/// `Remark(string text,uint64 nonce)`, a `System::remark_with_event`.
+	Remark { text: Vec<u8> },
+}
+
+pub struct RuntimeCallTemplates;
+impl pallet_evm_account_mapping::CallTemplates<RuntimeCall> for RuntimeCallTemplates {
+	type Template = RuntimeCallTemplate;
+
+	fn members(
+		template: &RuntimeCallTemplate,
+	) -> (&'static str, Vec<(&'static str, pallet_evm_account_mapping::TemplateValue)>) {
+		use pallet_evm_account_mapping::TemplateValue;
+		use sp_core::crypto::Ss58Codec;
+
+		match template {
+			RuntimeCallTemplate::Transfer { to, amount } => {
+				let ss58_prefix = <SS58Prefix as frame_support::traits::Get<u8>>::get();
+				let to = to.to_ss58check_with_version(ss58_prefix.into());
+				(
+					"Transfer",
+					alloc::vec![
+						("to", TemplateValue::String(to.into_bytes())),
+						("amount", TemplateValue::Uint((*amount).into())),
+					],
+				)
+			},
+			RuntimeCallTemplate::Remark { text } =>
+				("Remark", alloc::vec![("text", TemplateValue::String(text.clone()))]),
+		}
+	}
+
+	fn into_call(template: RuntimeCallTemplate) -> Option<RuntimeCall> {
+		match template {
+			RuntimeCallTemplate::Transfer { to, amount } =>
+				Some(RuntimeCall::Balances(BalancesCall::transfer_keep_alive {
+					dest: sp_runtime::MultiAddress::Id(to),
+					value: amount,
+				})),
+			RuntimeCallTemplate::Remark { text } => core::str::from_utf8(&text)
+				.is_ok()
+				.then(|| RuntimeCall::System(SystemCall::remark_with_event { remark: text })),
+		}
+	}
+}
+
 const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 
 parameter_types! {
@@ -274,7 +357,23 @@ parameter_types! {
 	pub EIP712Name: Vec<u8> = b"Substrate".to_vec();
 	pub EIP712Version: Vec<u8> = b"1".to_vec();
 	pub EIP712ChainID: pallet_evm_account_mapping::EIP712ChainID = sp_core::U256::from(0);
+	pub EIP712ExtraChainIDs: Vec<pallet_evm_account_mapping::EIP712ChainID> = Vec::new();
 	pub EIP712VerifyingContractAddress: pallet_evm_account_mapping::EIP712VerifyingContractAddress = sp_core::H160::from([0u8; 20]);
+	pub EIP712Salt: Option<pallet_evm_account_mapping::EIP712Salt> = None;
+	pub const RelayerServiceFeeShare: Perbill = Perbill::from_percent(50);
+	pub const MaxMetaCallBlockShare: Perbill = Perbill::from_percent(50);
+}
+
+/// The calls fresh accounts can make with the free quota of meta-calls.
+pub struct RuntimeFreeCallFilter;
+impl frame_support::traits::Contains<RuntimeCall> for RuntimeFreeCallFilter {
+	fn contains(call: &RuntimeCall) -> bool {
+		matches!(
+			call,
+			RuntimeCall::System(SystemCall::remark { .. }) |
+				RuntimeCall::System(SystemCall::remark_with_event { .. })
+		)
+	}
 }
 
 impl pallet_evm_account_mapping::Config for Runtime {
@@ -284,11 +383,33 @@ impl pallet_evm_account_mapping::Config for Runtime {
 	type AddressConverter = pallet_evm_account_mapping::SubstrateAddressConverter;
 	type ServiceFee = ConstU128<10000000000>;
 	type OnUnbalancedForServiceFee = DealWithServiceFee;
-	type CallFilter = frame_support::traits::Everything;
+	type CallFilter = pallet_evm_account_mapping::ContainsCall<frame_support::traits::Everything>;
+	type CallDescriber = RuntimeCallDescriber;
+	type CallTemplates = RuntimeCallTemplates;
+	type MaxBatchCalls = ConstU32<16>;
+	type MaxPaymasterCalls = ConstU32<16>;
+	type PaymasterDeposit = ConstU128<10000000000>;
+	type PaymasterUserDeposit = ConstU128<1000000000>;
+	type MaxPaymasterUsers = ConstU32<1024>;
+	type RelayerServiceFeeShare = RelayerServiceFeeShare;
+	type MaxBundleItems = ConstU32<256>;
+	type FreeCallFilter = RuntimeFreeCallFilter;
+	type FreeCallsPerPeriod = ConstU32<3>;
+	type MaxFreeCallsPerPeriod = ConstU32<10000>;
+	type FreeCallPeriod = ConstU32<DAYS>;
+	type FreeCallBalanceThreshold = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type MaxMetaCallsPerPeriod = ConstU32<100>;
+	type MaxNonceLanes = ConstU32<16>;
+	type MetaCallRatePeriod = ConstU32<HOURS>;
+	type MaxMetaCallBlockShare = MaxMetaCallBlockShare;
 	type EIP712Name = EIP712Name;
 	type EIP712Version = EIP712Version;
 	type EIP712ChainID = EIP712ChainID;
+	type EIP712ExtraChainIDs = EIP712ExtraChainIDs;
 	type EIP712VerifyingContractAddress = EIP712VerifyingContractAddress;
+	type EIP712Salt = EIP712Salt;
+	type EIP712DomainOrigin = frame_system::EnsureRoot<AccountId>;
+	type AdminOrigin = frame_system::EnsureRoot<AccountId>;
 	type WeightInfo = pallet_evm_account_mapping::weights::SubstrateWeight<Runtime>;
 }
 
@@ -529,6 +650,96 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_evm_account_mapping::runtime_api::EvmAccountMappingApi<Block, AccountId, Balance, BlockNumber>
+		for Runtime
+	{
+		fn account_nonce(who: AccountId) -> pallet_evm_account_mapping::Nonce {
+			EvmAccountMapping::account_nonce(&who)
+		}
+		fn lane_nonce(
+			who: AccountId,
+			nonce_key: pallet_evm_account_mapping::NonceKey,
+		) -> pallet_evm_account_mapping::Nonce {
+			EvmAccountMapping::lane_nonce(&who, nonce_key)
+		}
+		fn account_of(evm_public_key: Vec<u8>) -> Option<AccountId> {
+			EvmAccountMapping::account_of(&evm_public_key)
+		}
+		fn account_of_address(evm_address: sp_core::H160) -> Option<AccountId> {
+			EvmAccountMapping::account_of_address(&evm_address)
+		}
+		fn eip712_message_hash(
+			who: AccountId,
+			call_data: Vec<u8>,
+			nonce: pallet_evm_account_mapping::Nonce,
+		) -> pallet_evm_account_mapping::Keccak256Signature {
+			EvmAccountMapping::eip712_message_hash(who, &call_data, nonce)
+		}
+		fn eip712_message_hash_v2(
+			who: AccountId,
+			call_data: Vec<u8>,
+			nonce: pallet_evm_account_mapping::Nonce,
+			terms: pallet_evm_account_mapping::MetaCallTerms<Balance, BlockNumber>,
+		) -> pallet_evm_account_mapping::Keccak256Signature {
+			EvmAccountMapping::eip712_message_hash_v2(who, &call_data, nonce, &terms)
+		}
+		fn eip712_batch_message_hash(
+			who: AccountId,
+			calls: Vec<Vec<u8>>,
+			nonce: pallet_evm_account_mapping::Nonce,
+			atomic: bool,
+			max_fee: Balance,
+			valid_until: BlockNumber,
+		) -> pallet_evm_account_mapping::Keccak256Signature {
+			EvmAccountMapping::eip712_batch_message_hash(
+				who,
+				&calls,
+				nonce,
+				atomic,
+				max_fee,
+				valid_until,
+			)
+		}
+		fn eip712_sponsored_message_hash(
+			who: AccountId,
+			call_data: Vec<u8>,
+			nonce: pallet_evm_account_mapping::Nonce,
+			valid_until: BlockNumber,
+			sponsor: Option<AccountId>,
+		) -> pallet_evm_account_mapping::Keccak256Signature {
+			EvmAccountMapping::eip712_sponsored_message_hash(
+				who,
+				&call_data,
+				nonce,
+				valid_until,
+				sponsor.as_ref(),
+			)
+		}
+		fn eip712_cancel_message_hash(
+			who: AccountId,
+			nonce_key: pallet_evm_account_mapping::NonceKey,
+			up_to: pallet_evm_account_mapping::Nonce,
+			valid_until: BlockNumber,
+		) -> pallet_evm_account_mapping::Keccak256Signature {
+			EvmAccountMapping::eip712_cancel_message_hash(who, nonce_key, up_to, valid_until)
+		}
+		fn describe_call(call_data: Vec<u8>) -> Option<pallet_evm_account_mapping::CallDescription> {
+			EvmAccountMapping::describe_call(&call_data)
+		}
+		fn template_members(template: Vec<u8>) -> Option<pallet_evm_account_mapping::TemplateMembers> {
+			EvmAccountMapping::template_members(&template)
+		}
+		fn eip712_domain() -> pallet_evm_account_mapping::EIP712DomainParams {
+			pallet_evm_account_mapping::CurrentEIP712Domain::<Runtime>::get()
+		}
+		fn eip712_salt() -> Option<pallet_evm_account_mapping::EIP712Salt> {
+			<<Runtime as pallet_evm_account_mapping::Config>::EIP712Salt as frame_support::traits::Get<_>>::get()
+		}
+		fn typed_data_context() -> pallet_evm_account_mapping::TypedDataContext {
+			EvmAccountMapping::typed_data_context()
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (